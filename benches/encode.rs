@@ -0,0 +1,111 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use enigma::Enigma;
+
+const M3: &[&str] = &["ii", "iv", "v"];
+const M4: &[&str] = &["beta", "v", "vi", "viii"];
+
+const STECKER: &[(char, char)] = &[
+    ('A', 'V'),
+    ('B', 'S'),
+    ('C', 'G'),
+    ('D', 'L'),
+    ('F', 'U'),
+    ('H', 'Z'),
+    ('I', 'N'),
+    ('K', 'M'),
+    ('O', 'W'),
+    ('R', 'X'),
+];
+
+/// All thirteen cables in, so every letter goes through the plugboard.
+const FULL_STECKER: &[(char, char)] = &[
+    ('A', 'N'),
+    ('B', 'O'),
+    ('C', 'P'),
+    ('D', 'Q'),
+    ('E', 'R'),
+    ('F', 'S'),
+    ('G', 'T'),
+    ('H', 'U'),
+    ('I', 'V'),
+    ('J', 'W'),
+    ('K', 'X'),
+    ('L', 'Y'),
+    ('M', 'Z'),
+];
+
+/// Name, rotor order and plug pairs of a machine to push text through.
+type Setup = (
+    &'static str,
+    &'static [&'static str],
+    &'static [(char, char)],
+);
+
+fn message(len: usize) -> String {
+    "AUFKLXABTEILUNGXVONXKURTINOWAXKURTINOWAXNORDWESTLXSEBEZ"
+        .chars()
+        .cycle()
+        .take(len)
+        .collect()
+}
+
+fn machine(rotors: &[&str], plugs: &[(char, char)]) -> Enigma {
+    let (reflector, rings) = match rotors.len() {
+        4 => ("cthin", "ABUL"),
+        _ => ("ukwb", "BUL"),
+    };
+
+    Enigma::from_config(rotors, reflector, None, Some(rings), Some(plugs)).unwrap()
+}
+
+fn from_config(c: &mut Criterion) {
+    let mut group = c.benchmark_group("from_config");
+
+    group.bench_function("m3", |b| {
+        b.iter(|| Enigma::from_config(black_box(M3), "ukwb", Some("BLA"), Some("BUL"), None))
+    });
+    group.bench_function("m4", |b| {
+        b.iter(|| Enigma::from_config(black_box(M4), "cthin", Some("VJNA"), Some("AAEL"), None))
+    });
+    group.bench_function("m3_stecker", |b| {
+        b.iter(|| {
+            Enigma::from_config(
+                black_box(M3),
+                "ukwb",
+                Some("BLA"),
+                Some("BUL"),
+                Some(STECKER),
+            )
+        })
+    });
+
+    group.finish();
+}
+
+fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+
+    let setups: [Setup; 4] = [
+        ("m3", M3, &[]),
+        ("m3_stecker", M3, STECKER),
+        ("m3_full_stecker", M3, FULL_STECKER),
+        ("m4_stecker", M4, STECKER),
+    ];
+
+    for len in [25, 10_000] {
+        let plaintext = message(len);
+        group.throughput(Throughput::Elements(len as u64));
+
+        for (name, rotors, plugs) in setups {
+            group.bench_with_input(BenchmarkId::new(name, len), &plaintext, |b, plaintext| {
+                let mut enigma = machine(rotors, plugs);
+                b.iter(|| enigma.encode(black_box(plaintext)))
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, from_config, encode);
+criterion_main!(benches);
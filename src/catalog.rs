@@ -0,0 +1,324 @@
+use std::{fs, path::Path, str::FromStr};
+
+use serde::Deserialize;
+
+use crate::{
+    enigma::Enigma,
+    error::EnigmaError,
+    rotors::{Entry, Reflector, Rotor, Rotors, Wheel},
+};
+
+/// Rotors and reflectors wired to order, looked up by name alongside the
+/// built-in ones. A catalog file lists them as
+///
+/// ```toml
+/// [[rotors]]
+/// name = "x-i"
+/// wiring = "EKMFLGDQVZNTOWYHXUSPAIBRCJ"
+/// notches = "Q"
+///
+/// [[reflectors]]
+/// name = "x-ukw"
+/// wiring = "YRUHQSLDPXNGOKMIEBFZCWVJAT"
+///
+/// [[entries]]
+/// name = "x-etw"
+/// wiring = "QWERTZUIOASDFGHJKPYXCVBNML"
+/// ```
+///
+/// or the same in JSON, `{"rotors": [...], "reflectors": [...], ...}`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Catalog {
+    rotors: Vec<Rotor>,
+    reflectors: Vec<Reflector>,
+    entries: Vec<Entry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Entries {
+    #[serde(default)]
+    rotors: Vec<RotorEntry>,
+    #[serde(default)]
+    reflectors: Vec<ReflectorEntry>,
+    #[serde(default)]
+    entries: Vec<ReflectorEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RotorEntry {
+    name: String,
+    wiring: String,
+    #[serde(default)]
+    notches: String,
+}
+
+/// A reflector or entry wheel, which have no notches.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ReflectorEntry {
+    name: String,
+    wiring: String,
+}
+
+impl Catalog {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn from_toml(s: &str) -> Result<Self, EnigmaError> {
+        let entries = toml::from_str(s).map_err(|e| EnigmaError::InvalidCatalog(e.to_string()))?;
+        Catalog::from_entries(entries)
+    }
+
+    pub fn from_json(s: &str) -> Result<Self, EnigmaError> {
+        let entries =
+            serde_json::from_str(s).map_err(|e| EnigmaError::InvalidCatalog(e.to_string()))?;
+        Catalog::from_entries(entries)
+    }
+
+    /// Reads a catalog file, as JSON when it ends in `.json` and TOML
+    /// otherwise.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, EnigmaError> {
+        let path = path.as_ref();
+        let s = fs::read_to_string(path)
+            .map_err(|e| EnigmaError::InvalidCatalog(format!("{}: {}", path.display(), e)))?;
+
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("json") => Catalog::from_json(&s),
+            _ => Catalog::from_toml(&s),
+        }
+    }
+
+    fn from_entries(entries: Entries) -> Result<Self, EnigmaError> {
+        let mut catalog = Catalog::new();
+
+        for x in entries.rotors {
+            catalog.add_rotor(Rotor::custom(&x.name, &x.wiring, &x.notches)?)?;
+        }
+
+        for x in entries.reflectors {
+            catalog.add_reflector(Reflector::custom(&x.name, &x.wiring)?)?;
+        }
+
+        for x in entries.entries {
+            catalog.add_entry(Entry::custom(&x.name, &x.wiring)?)?;
+        }
+
+        Ok(catalog)
+    }
+
+    /// Adds a rotor under its own name, which mustn't be taken already.
+    pub fn add_rotor(&mut self, rotor: Rotor) -> Result<(), EnigmaError> {
+        let name = rotor.to_string();
+        if Rotor::from_str(&name).is_ok() || self.find_rotor(&name).is_some() {
+            return Err(EnigmaError::InvalidCatalog(format!(
+                "{:?} is already a rotor",
+                name
+            )));
+        }
+
+        self.rotors.push(rotor);
+        Ok(())
+    }
+
+    /// Adds a reflector under its own name, which mustn't be taken already.
+    pub fn add_reflector(&mut self, reflector: Reflector) -> Result<(), EnigmaError> {
+        let name = reflector.to_string();
+        if Reflector::from_str(&name).is_ok() || self.find_reflector(&name).is_some() {
+            return Err(EnigmaError::InvalidCatalog(format!(
+                "{:?} is already a reflector",
+                name
+            )));
+        }
+
+        self.reflectors.push(reflector);
+        Ok(())
+    }
+
+    /// Adds an entry wheel under its own name, which mustn't be taken already.
+    pub fn add_entry(&mut self, entry: Entry) -> Result<(), EnigmaError> {
+        let name = entry.to_string();
+        if Entry::from_str(&name).is_ok() || self.find_entry(&name).is_some() {
+            return Err(EnigmaError::InvalidCatalog(format!(
+                "{:?} is already an entry wheel",
+                name
+            )));
+        }
+
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Looks up a rotor by name, in the catalog first and then the built-ins.
+    pub fn rotor(&self, name: &str) -> Result<Rotor, EnigmaError> {
+        match self.find_rotor(name) {
+            Some(r) => Ok(r.clone()),
+            None => Rotor::from_str(name).map_err(|_| EnigmaError::UnknownRotor(name.to_string())),
+        }
+    }
+
+    /// Looks up a reflector by name, in the catalog first and then the
+    /// built-ins.
+    pub fn reflector(&self, name: &str) -> Result<Reflector, EnigmaError> {
+        match self.find_reflector(name) {
+            Some(r) => Ok(r.clone()),
+            None => Reflector::from_str(name)
+                .map_err(|_| EnigmaError::UnknownReflector(name.to_string())),
+        }
+    }
+
+    /// Looks up an entry wheel by name, in the catalog first and then the
+    /// built-ins.
+    pub fn entry(&self, name: &str) -> Result<Entry, EnigmaError> {
+        match self.find_entry(name) {
+            Some(e) => Ok(e.clone()),
+            None => Entry::from_str(name).map_err(|_| EnigmaError::UnknownEntry(name.to_string())),
+        }
+    }
+
+    /// Picks out rotors and a reflector by name, leftmost rotor first, to
+    /// build a machine from with `Enigma::from_rotors`. The entry wheel is
+    /// left to the model.
+    pub fn rotors(&self, rotors: &[&str], reflector: &str) -> Result<Rotors, EnigmaError> {
+        Ok(Rotors {
+            reflector: self.reflector(reflector)?,
+            rotors: rotors
+                .iter()
+                .map(|x| self.rotor(x).map(Wheel::Rotor))
+                .collect::<Result<_, _>>()?,
+            entry: None,
+        })
+    }
+
+    /// Builds a machine as `Enigma::from_config` does, with the catalog's
+    /// wheels to pick from as well; the same as `Enigma::from_catalog`.
+    pub fn from_config(
+        &self,
+        rotors: &[&str],
+        reflector: &str,
+        code_settings: Option<&str>,
+        ring_settings: Option<&str>,
+        plug_settings: Option<&[(char, char)]>,
+    ) -> Result<Enigma, EnigmaError> {
+        Enigma::from_catalog(
+            self,
+            rotors,
+            reflector,
+            code_settings,
+            ring_settings,
+            plug_settings,
+        )
+    }
+
+    fn find_rotor(&self, name: &str) -> Option<&Rotor> {
+        self.rotors
+            .iter()
+            .find(|r| r.to_string().eq_ignore_ascii_case(name))
+    }
+
+    fn find_reflector(&self, name: &str) -> Option<&Reflector> {
+        self.reflectors
+            .iter()
+            .find(|r| r.to_string().eq_ignore_ascii_case(name))
+    }
+
+    fn find_entry(&self, name: &str) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .find(|e| e.to_string().eq_ignore_ascii_case(name))
+    }
+}
+
+#[test]
+pub fn load_catalog() {
+    // the Enigma I's wheels under other names encipher as the originals
+    let catalog = Catalog::from_toml(
+        r#"
+        [[rotors]]
+        name = "x-i"
+        wiring = "EKMFLGDQVZNTOWYHXUSPAIBRCJ"
+        notches = "Q"
+
+        [[rotors]]
+        name = "x-ii"
+        wiring = "ajdksiruxblhwtmcqgznpyfvoe"
+        notches = "E"
+
+        [[reflectors]]
+        name = "x-ukw"
+        wiring = "YRUHQSLDPXNGOKMIEBFZCWVJAT"
+
+        [[entries]]
+        name = "x-etw"
+        wiring = "QWERTZUIOASDFGHJKPYXCVBNML"
+        "#,
+    )
+    .unwrap();
+
+    let mut enigma = catalog
+        .from_config(&["x-i", "X-II", "iii"], "x-ukw", None, None, None)
+        .unwrap();
+    assert_eq!(enigma.encode("AAAAA"), "BDZGO");
+    let mut enigma =
+        Enigma::from_catalog(&catalog, &["x-i", "x-ii", "iii"], "x-ukw", None, None, None).unwrap();
+    assert_eq!(enigma.encode("AAAAA"), "BDZGO");
+    assert_eq!(
+        Enigma::from_config(&["x-i", "x-ii", "iii"], "x-ukw", None, None, None),
+        Err(EnigmaError::UnknownRotor("x-i".to_string()))
+    );
+
+    let mut wheels = catalog.rotors(&["x-i", "x-ii", "iii"], "x-ukw").unwrap();
+    wheels.entry = Some(catalog.entry("x-etw").unwrap());
+    let mut enigma = Enigma::from_rotors(wheels, None, None, None).unwrap();
+    assert_eq!(enigma.encode("AAAAA"), "YWRLJ");
+    assert_eq!(catalog.entry("qwertzu"), Ok(Entry::QWERTZU));
+    assert!(matches!(&enigma.wheels.rotors[2], Wheel::Rotor(r) if r.to_string() == "x-i"));
+
+    let catalog = Catalog::from_json(
+        r#"{"rotors": [{"name": "mine", "wiring": "BCDEFGHIJKLMNOPQRSTUVWXYZA", "notches": "AN"}]}"#,
+    )
+    .unwrap();
+    assert_eq!(catalog.rotor("mine").unwrap().turnovers(), "AN");
+    assert_eq!(catalog.reflector("ukwc"), Ok(Reflector::UKWC));
+    assert_eq!(
+        catalog.rotor("yours"),
+        Err(EnigmaError::UnknownRotor("yours".to_string()))
+    );
+}
+
+#[test]
+pub fn load_bad_catalog() {
+    let rotor = |wiring: &str| {
+        Catalog::from_json(&format!(
+            r#"{{"rotors": [{{"name": "bad", "wiring": "{}"}}]}}"#,
+            wiring
+        ))
+    };
+    let reflector = |wiring: &str| {
+        Catalog::from_json(&format!(
+            r#"{{"reflectors": [{{"name": "bad", "wiring": "{}"}}]}}"#,
+            wiring
+        ))
+    };
+    let invalid = Err(EnigmaError::InvalidWiring("bad".to_string()));
+
+    assert_eq!(rotor("ABC"), invalid);
+    assert_eq!(rotor("AACDEFGHIJKLMNOPQRSTUVWXYZ"), invalid);
+    assert_eq!(rotor("ABCDEFGHIJKLMNOPQRSTUVWXY1"), invalid);
+    assert!(rotor("ABCDEFGHIJKLMNOPQRSTUVWXYZ").is_ok());
+    // a rotor wiring, but not pairs
+    assert_eq!(reflector("EKMFLGDQVZNTOWYHXUSPAIBRCJ"), invalid);
+    // pairs, but with A and B wired to themselves
+    assert_eq!(reflector("ABDCFEHGJILKNMPORQTSVUXWZY"), invalid);
+
+    assert!(matches!(
+        Catalog::from_toml("[[rotors]]\nname = \"iv\"\nwiring = \"ABCDEFGHIJKLMNOPQRSTUVWXYZ\""),
+        Err(EnigmaError::InvalidCatalog(_))
+    ));
+    assert!(matches!(
+        Catalog::from_toml("[[rotor]]\nname = \"x\""),
+        Err(EnigmaError::InvalidCatalog(_))
+    ));
+}
@@ -1,283 +1,418 @@
-use crate::rotors::{Rotor, Wheel};
-use crate::util::abc;
+use crate::rotors::{Reflector, Rotor, Wheel};
+
+/// Contact tables for one rotor: where each contact on the right face comes
+/// out on the left (`forward`) and the way back (`inverse`), as offsets 0-25.
+pub(crate) type Wiring = [u8; 26];
+
+/// How a keypress moves the wheels.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub(crate) enum Drive {
+    /// Pawls and ratchets, which double step the middle rotor.
+    #[default]
+    Lever,
+    /// The Enigma G's cog wheels, an odometer that carries through every
+    /// rotor and on into the reflector.
+    Gear,
+}
 
+/// Wheel positions are kept in the order the current first meets the wheels:
+/// index 0 is the fast (rightmost) rotor and index 3 the Greek wheel of an M4,
+/// which is set but never stepped. The reflector comes right after the last
+/// rotor, and stays at A on the machines where it can't be set.
 #[derive(Debug, PartialEq, Clone)]
 pub struct Circuit {
-    pub code: [usize; 3],
-    pub node: Index,
+    pub code: [usize; 5],
+    pub rings: [usize; 5],
     pub step: bool,
+    /// The window letters the wheels were set to, for going back to.
+    pub(crate) start: [usize; 5],
 
     pub(crate) len: usize,
-    pub(crate) turnovers: Vec<String>,
-}
-
-#[derive(Debug, PartialEq, Clone)]
-pub struct Index {
-    pub front: usize,
-    pub back: isize,
+    pub(crate) drive: Drive,
+    /// The entry wheel: the contact each key is wired to, and back.
+    pub(crate) entry: Wiring,
+    pub(crate) exit: Wiring,
+    pub(crate) forward: [Wiring; 4],
+    pub(crate) inverse: [Wiring; 4],
+    pub(crate) reflector: Wiring,
+    /// Window letters each rotor carries a notch at, one bit per letter.
+    pub(crate) turnovers: [u32; 4],
 }
 
 impl Circuit {
-    pub fn new(rotors: &Vec<Wheel>, code: [usize; 3]) -> Self {
-        let turnovers = rotors
-            .iter()
-            .map(|r| r.turnovers().to_string())
-            .collect::<Vec<_>>();
-
-        let len = rotors.len() + 1; // plus reflector
-        
-        // stopover
-        let back;
-        match len > 4 {
-            true => back = 8 / 2 - 1,
-            false => back = 6 / 2 - 1,
-        };
-
-        Circuit {
+    /// Wires up `rotors`, fast rotor first, with the reflector last, behind
+    /// an entry wheel given as the keys wired to contacts A to Z.
+    pub(crate) fn new(
+        rotors: &[Wheel],
+        entry: &str,
+        drive: Drive,
+        code: [usize; 5],
+        rings: [usize; 5],
+    ) -> Self {
+        let (reflector, rotors) = rotors.split_last().unwrap();
+
+        let mut circuit = Circuit {
             code,
-            node: Index { front: 0, back },
+            rings,
             step: false,
-            len,
-            turnovers,
-        }
-    }
-
-    pub fn reset(&mut self) {
-        // stopover
-        let back;
-        match self.len > 4 {
-            true => back = 8 / 2 - 1,
-            false => back = 6 / 2 - 1,
+            start: code,
+            len: rotors.len(),
+            drive,
+            entry: [0; 26],
+            exit: wiring(entry),
+            forward: [[0; 26]; 4],
+            inverse: [[0; 26]; 4],
+            reflector: wiring(reflector.alpha()),
+            turnovers: [0; 4],
         };
 
-        self.node = Index { front: 0, back };
-    }
-
-    pub fn signal(&self) -> Box<dyn Fn(u32, [usize; 3]) -> isize + '_> {
-        let mut connections = vec!["2", "12", "01", "0"]; // maybe reimplement this
-        let mut len = connections.len();
-
-        if self.stopover() % 2 == 1 {
-            connections.push("42");
-            len = connections.len();
-        }
-
-        let at_end = self.node.front > len - 1;
-        if at_end {
-            connections.reverse();
+        for (contact, key) in circuit.exit.iter().enumerate() {
+            circuit.entry[*key as usize] = contact as u8;
         }
 
-        let idx = self.node.front % len;
-        let mut connection = connections[idx]
-            .split_inclusive("")
-            .filter(|s| *s != "")
-            .collect::<Vec<_>>();
-
-        if at_end {
-            connection.reverse()
-        }
-
-        match connection.len() - 1 == 1 {
-            true => {
-                if connection.contains(&"4") {
-                    Box::new(move |a: u32, _b: [usize; 3]| {
-                        let alpha = abc!()
-                            .iter()
-                            .position(|c| *c == char::from_u32(a).unwrap_or('\0') as u32)
-                            .unwrap();
-                        alpha as isize
-                    })
-                } else {
-                    Box::new(move |a: u32, b: [usize; 3]| {
-                        let alpha = abc!()
-                            .iter()
-                            .position(|c| *c == char::from_u32(a).unwrap_or('\0') as u32)
-                            .unwrap();
-
-                        let wheels = [
-                            connection[0].parse::<usize>().unwrap(),
-                            connection[1].parse::<usize>().unwrap(),
-                        ];
-
-                        let mut sig =
-                            alpha as isize + (b[wheels[0]] as isize - b[wheels[1]] as isize);
-                        sig = sig % 26;
-
-                        match sig < 0 {
-                            true => sig + 26,
-                            false => sig,
-                        }
-                    })
-                }
+        for (i, rotor) in rotors.iter().enumerate() {
+            circuit.forward[i] = wiring(rotor.alpha());
+            for (contact, out) in circuit.forward[i].iter().enumerate() {
+                circuit.inverse[i][*out as usize] = contact as u8;
             }
-            false => {
-                let node_parity;
-                match len > 4 && at_end {
-                    true => node_parity = idx % 2 == 0,
-                    false => node_parity = idx % 2 == 1,
-                };
-
-                if node_parity {
-                    Box::new(move |a: u32, b: [usize; 3]| {
-                        let alpha = abc!()
-                            .iter()
-                            .position(|c| *c == char::from_u32(a).unwrap_or('\0') as u32)
-                            .unwrap();
-
-                        let wheel = connections[self.node.front % len].parse::<usize>().unwrap();
-                        let mut sig = alpha as isize - b[wheel] as isize;
-                        sig = sig % 26;
-
-                        match sig < 0 {
-                            true => sig + 26,
-                            false => sig,
-                        }
-                    })
-                } else {
-                    Box::new(move |a: u32, b: [usize; 3]| {
-                        let alpha = abc!()
-                            .iter()
-                            .position(|c| *c == char::from_u32(a).unwrap_or('\0') as u32)
-                            .unwrap();
-
-                        let wheel = connections[self.node.front % len].parse::<usize>().unwrap();
-
-                        let mut sig = alpha as isize + b[wheel] as isize;
-                        sig = sig % 26;
-
-                        match sig < 0 {
-                            true => sig + 26,
-                            false => sig,
-                        }
-                    })
-                }
-            }
-        }
-    }
 
-    pub fn pathway(&self) -> u8 {
-        match self.len > 4 {
-            true => 8,
-            false => 6,
+            circuit.turnovers[i] = rotor
+                .turnovers()
+                .bytes()
+                .fold(0, |notches, n| notches | 1 << (n - b'A'));
         }
-    }
 
-    pub fn stopover(&self) -> u8 {
-        self.pathway() / 2 - 1
+        circuit
     }
 
-    fn node_step(&mut self) -> bool {
-        if self.node.front <= self.pathway().into() {
-            if !(self.node.front < self.len) {
-                self.node.back -= 1;
-            }
-            self.node.front += 1;
+    /// Offsets of the wiring cores against the contacts, i.e. the window
+    /// letters less the ring settings. These, not the raw window letters, are
+    /// what the current is shifted by going in and out of each rotor.
+    pub fn offsets(&self) -> [usize; 5] {
+        let mut offsets = [0; 5];
+        for (i, offset) in offsets.iter_mut().enumerate() {
+            *offset = (self.code[i] + 26 - self.rings[i]) % 26;
         }
+        offsets
+    }
 
-        self.node.front <= self.pathway().into()
+    fn notched(&self, wheel: usize) -> bool {
+        wheel < self.len && self.turnovers[wheel] >> self.code[wheel] & 1 == 1
     }
 
+    /// Steps the wheels for a single keypress. The notches sit on the alphabet
+    /// rings, so turnovers are read off the window letters in `code`; moving a
+    /// ring shifts the notch against the wiring, which `offsets` accounts for.
     pub fn accrue(&mut self) {
-        let etw = ('A'..='Z').into_iter().collect::<Vec<_>>();
+        if self.drive == Drive::Gear {
+            // each wheel carries the next one along from a notch, with no
+            // double step, up to the reflector after the last rotor
+            for wheel in 0..=self.len {
+                let carry = self.notched(wheel);
+                self.code[wheel] = (self.code[wheel] + 1) % 26;
+                if !carry {
+                    break;
+                }
+            }
 
-        let mut turnover = self.turnovers[1].split_inclusive("").collect::<Vec<_>>();
-        let mut index = self.code[1] as usize;
+            return;
+        }
 
-        if !turnover.contains(&etw[index].to_string().as_str()) {
+        if !self.notched(1) {
             self.step = false;
         }
 
-        if turnover.contains(&etw[index].to_string().as_str()) && !self.step {
-            self.code[2] = (self.code[2] + 1) % 26;
-            self.code[1] = (self.code[1] + 1) % 26;
+        if self.notched(1) && !self.step {
             self.code[0] = (self.code[0] + 1) % 26;
+            self.code[1] = (self.code[1] + 1) % 26;
+            self.code[2] = (self.code[2] + 1) % 26;
 
             self.step = true;
             return;
         }
 
-        turnover = self.turnovers[0].split_inclusive("").collect::<Vec<_>>();
-        index = self.code[2] as usize;
-
-        if turnover.contains(&etw[index].to_string().as_str()) {
-            self.code[2] = (self.code[2] + 1) % 26;
+        if self.notched(0) {
+            self.code[0] = (self.code[0] + 1) % 26;
             self.code[1] = (self.code[1] + 1) % 26;
 
             return;
         }
 
-        self.code[2] = (self.code[2] + 1) % 26;
+        self.code[0] = (self.code[0] + 1) % 26;
+    }
+
+    /// Steps the wheels as `presses` keypresses would, without going through
+    /// them one by one. The gear drive is an odometer, so each wheel's
+    /// carries are counted straight off its notches. The levers' double step
+    /// isn't, so they're turned 26 presses at a time, the fast rotor back
+    /// where it started after each; the rest can only be in 26 * 26 * 2
+    /// states by then, and once one comes round again whole cycles are
+    /// skipped.
+    pub fn advance(&mut self, presses: usize) {
+        if self.drive == Drive::Gear {
+            let mut steps = presses;
+            for wheel in 0..=self.len {
+                let from = self.code[wheel];
+                self.code[wheel] = (from + steps % 26) % 26;
+                if wheel == self.len {
+                    break;
+                }
+
+                // a carry for every notch the wheel turns off
+                let notches = self.turnovers[wheel];
+                let partial = (0..steps % 26)
+                    .filter(|n| notches >> ((from + n) % 26) & 1 == 1)
+                    .count();
+                steps = steps / 26 * notches.count_ones() as usize + partial;
+            }
+
+            return;
+        }
+
+        // blocks still to go, by the middle and left rotors and the latch
+        // they were at
+        let mut seen = [usize::MAX; 26 * 26 * 2];
+        let mut blocks = presses / 26;
+
+        while blocks > 0 {
+            let state = (self.code[1] * 26 + self.code[2]) * 2 + self.step as usize;
+            if seen[state] != usize::MAX {
+                blocks %= seen[state] - blocks;
+                seen = [usize::MAX; 26 * 26 * 2];
+                continue;
+            }
+
+            seen[state] = blocks;
+            self.turn(26);
+            blocks -= 1;
+        }
+
+        self.turn(presses % 26);
     }
 
-    pub fn scramble(&mut self, ch: &char, rotors: &Vec<Wheel>) -> char {
-        let mut pch = ch.to_owned();
-
-        loop {
-            if self.node.front < self.len {
-                pch = rotors[self.node.front].alpha().chars().collect::<Vec<_>>()
-                    [self.signal()(pch as u32, self.code) as usize];
-            } else {
-                let alpha = abc!();
-
-                let tch =
-                    char::from_u32(alpha[self.signal()(pch as u32, self.code) as usize]).unwrap();
-
-                pch = char::from_u32(
-                    alpha[rotors[self.node.back as usize]
-                        .alpha()
-                        .chars()
-                        .position(|c| c == tch)
-                        .unwrap()],
-                )
-                .unwrap();
+    /// Steps the wheels lever by lever for `presses` keypresses, skipping
+    /// over the runs where only the fast rotor moves.
+    fn turn(&mut self, mut presses: usize) {
+        while presses > 0 {
+            let quiet = self.quiet().unwrap_or(presses).min(presses);
+            if quiet > 0 {
+                self.code[0] = (self.code[0] + quiet) % 26;
+                if !self.notched(1) {
+                    self.step = false;
+                }
+                presses -= quiet;
             }
 
-            if !self.node_step() {
+            if presses == 0 {
                 break;
             }
+
+            self.accrue();
+            presses -= 1;
+        }
+    }
+
+    /// How many keypresses from here turn the fast rotor alone, or `None`
+    /// if nothing but the fast rotor will ever move.
+    fn quiet(&self) -> Option<usize> {
+        if self.notched(1) && !self.step {
+            return Some(0);
         }
 
-        pch
+        (0..26).find(|n| self.turnovers[0] >> ((self.code[0] + n) % 26) & 1 == 1)
+    }
+
+    /// Sends a key (0-25) in through the entry wheel and the rotors, off the
+    /// reflector and back, returning the key it lights up.
+    pub fn scramble(&self, key: u8) -> u8 {
+        let offsets = self.offsets();
+        let mut c = self.entry[key as usize] as usize;
+
+        for (wiring, offset) in self.forward[..self.len].iter().zip(offsets) {
+            c = (wiring[(c + offset) % 26] as usize + 26 - offset) % 26;
+        }
+
+        let offset = offsets[self.len];
+        c = (self.reflector[(c + offset) % 26] as usize + 26 - offset) % 26;
+
+        for (wiring, offset) in self.inverse[..self.len].iter().zip(offsets).rev() {
+            c = (wiring[(c + offset) % 26] as usize + 26 - offset) % 26;
+        }
+
+        self.exit[c]
     }
 }
 
+fn wiring(alpha: &str) -> Wiring {
+    let mut wiring = [0; 26];
+    for (contact, out) in wiring.iter_mut().zip(alpha.bytes()) {
+        *contact = out - b'A';
+    }
+    wiring
+}
+
 impl Default for Circuit {
     fn default() -> Self {
-        let rotors = vec![Rotor::I, Rotor::II, Rotor::III];
-        let turnovers = rotors
-            .iter()
-            .map(|r| r.turnovers().to_string())
-            .collect::<Vec<_>>();
-
-        Self {
-            code: [0, 0, 0],
-            node: Index {
-                front: 0,
-                back: 6 / 2 - 1,
-            },
-            step: false,
-            len: 4,
-            turnovers,
-        }
+        let wheels = [
+            Wheel::Rotor(Rotor::III),
+            Wheel::Rotor(Rotor::II),
+            Wheel::Rotor(Rotor::I),
+            Wheel::Reflector(Reflector::UKWB),
+        ];
+
+        Circuit::new(
+            &wheels,
+            "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+            Drive::Lever,
+            [0; 5],
+            [0; 5],
+        )
     }
 }
 
-impl Default for Index {
-    fn default() -> Self {
-        Self {
-            front: Default::default(),
-            back: Default::default(),
-        }
+#[test]
+pub fn test_wheel_stepping() {
+    // rotor III sits at the fast end, II in the middle: ADU steps through the
+    // middle rotor's double step to BFX
+    let mut circuit = Circuit {
+        code: [20, 3, 0, 0, 0],
+        ..Default::default()
+    };
+
+    let mut windows = vec![];
+    for _ in 0..4 {
+        circuit.accrue();
+        windows.push(circuit.code);
     }
+
+    assert_eq!(
+        windows,
+        [
+            [21, 3, 0, 0, 0],
+            [22, 4, 0, 0, 0],
+            [23, 5, 1, 0, 0],
+            [24, 5, 1, 0, 0]
+        ]
+    );
 }
 
 #[test]
-pub fn test_node_stepping() {
-    let mut circuit = Circuit::default();
+pub fn test_wiring_tables() {
+    let circuit = Circuit::default();
+
+    for c in 0..26 {
+        assert_eq!(circuit.inverse[0][circuit.forward[0][c] as usize], c as u8);
+        assert_ne!(circuit.scramble(c as u8), c as u8);
+        assert_eq!(circuit.scramble(circuit.scramble(c as u8)), c as u8);
+    }
+}
 
-    loop {
-        if !circuit.node_step() {
-            break;
+#[test]
+pub fn test_gear_stepping() {
+    // an odometer: Z carries into the next wheel, and only the wheel that
+    // is carried from moves on, so the middle rotor never double steps
+    let mut circuit = Circuit {
+        code: [24, 25, 25, 7, 0],
+        drive: Drive::Gear,
+        turnovers: [1 << 25, 1 << 25, 1 << 25, 0],
+        ..Default::default()
+    };
+
+    let mut windows = vec![];
+    for _ in 0..3 {
+        circuit.accrue();
+        windows.push(circuit.code);
+    }
+
+    assert_eq!(
+        windows,
+        [[25, 25, 25, 7, 0], [0, 0, 0, 8, 0], [1, 0, 0, 8, 0]]
+    );
+}
+
+#[test]
+pub fn test_advance() {
+    let rotors = |names: &[&str]| {
+        names
+            .iter()
+            .map(|r| Wheel::Rotor(r.parse().unwrap()))
+            .chain([Wheel::Reflector(Reflector::UKWB)])
+            .collect::<Vec<_>>()
+    };
+    let every = Rotor::custom(
+        "every",
+        "EKMFLGDQVZNTOWYHXUSPAIBRCJ",
+        "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+    )
+    .unwrap();
+    let none = Rotor::custom("none", "EKMFLGDQVZNTOWYHXUSPAIBRCJ", "").unwrap();
+
+    let circuits = [
+        // fast rotor first, so III turns over at V and II double steps at E
+        (rotors(&["iii", "ii", "i"]), Drive::Lever, [20, 3, 0, 0, 0]),
+        (
+            rotors(&["viii", "vi", "vii", "beta"]),
+            Drive::Lever,
+            [12, 25, 3, 7, 0],
+        ),
+        (
+            rotors(&["t-iv", "t-ii", "t-vii"]),
+            Drive::Lever,
+            [5, 9, 1, 0, 0],
+        ),
+        (
+            rotors(&["g-312-iii", "g-312-ii", "g-312-i"]),
+            Drive::Gear,
+            [3, 17, 25, 9, 0],
+        ),
+        (
+            vec![
+                Wheel::Rotor(every),
+                Wheel::Rotor(Rotor::II),
+                Wheel::Rotor(none.clone()),
+                Wheel::Reflector(Reflector::UKWB),
+            ],
+            Drive::Lever,
+            [0, 4, 0, 0, 0],
+        ),
+        (
+            vec![
+                Wheel::Rotor(none),
+                Wheel::Rotor(Rotor::II),
+                Wheel::Rotor(Rotor::I),
+                Wheel::Reflector(Reflector::UKWB),
+            ],
+            Drive::Gear,
+            [7, 0, 0, 0, 0],
+        ),
+    ];
+
+    for (wheels, drive, code) in circuits {
+        let start = Circuit::new(&wheels, "ABCDEFGHIJKLMNOPQRSTUVWXYZ", drive, code, [0; 5]);
+        let mut stepped = start.clone();
+
+        // every count to start with, then a spread past the longest cycle
+        let counts = (0..600).chain((600..40_000).step_by(997));
+        let mut stepped_to = 0;
+        for presses in counts {
+            while stepped_to < presses {
+                stepped.accrue();
+                stepped_to += 1;
+            }
+
+            let mut advanced = start.clone();
+            advanced.advance(presses);
+            assert_eq!(advanced.code, stepped.code, "{:?} {}", code, presses);
+            assert_eq!(advanced.step, stepped.step, "{:?} {}", code, presses);
         }
+
+        // as far as a count goes, in one go or two
+        let mut whole = start.clone();
+        whole.advance(usize::MAX);
+        let mut halves = start.clone();
+        halves.advance(usize::MAX / 2);
+        halves.advance(usize::MAX - usize::MAX / 2);
+        assert_eq!(whole.code, halves.code, "{:?}", code);
+        assert_eq!(whole.step, halves.step, "{:?}", code);
     }
 }
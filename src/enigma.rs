@@ -1,17 +1,52 @@
-use std::{collections::HashMap, iter::zip, str::FromStr};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    circuit::{Circuit, Index},
-    rotors::{Reflector, Rotor, Rotors, Wheel},
-    util::abc,
+    catalog::Catalog,
+    circuit::Circuit,
+    error::EnigmaError,
+    model::Model,
+    plugboard::Plugboard,
+    rotors::{Entry, Reflector, Rotor, Rotors, Wheel},
+    uhr::Uhr,
+    util::settings,
 };
 
-pub(crate) type Stecker = HashMap<u32, u32>;
-#[derive(Debug, PartialEq)]
+/// What the keyboard does with characters it has no key for. Letters are
+/// typed regardless of case and whitespace is always kept as it is.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Input {
+    /// Stop at the first one and report where it is.
+    #[default]
+    Reject,
+    Drop,
+    PassThrough,
+    /// Type this letter in its place.
+    Substitute(char),
+}
+
+/// Where the wheels stand, from `Enigma::snapshot`, to go back to with
+/// `Enigma::restore`. Only the moving parts are kept, so it's cheap to copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct State {
+    code: [usize; 5],
+    step: bool,
+}
+
+/// A machine saves as its wheels and settings together with where it has
+/// got to, so one stopped mid-message carries on exactly where it was.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "Config", try_from = "Config")]
 pub struct Enigma {
     pub wheels: Rotors,
+    model: Model,
     circuit: Circuit,
-    pub plugboard: Stecker,
+    plugboard: Plugboard,
+    /// An Uhr plugged in, which takes its twenty letters off the plugboard.
+    uhr: Option<Uhr>,
+    pub input: Input,
 }
 
 impl Enigma {
@@ -19,256 +54,556 @@ impl Enigma {
         Default::default()
     }
 
-    pub fn from_wheels(rotors: &[&str], reflector: &str) -> Self {
-        let mut _rotors = Rotors::default();
+    pub fn from_wheels(rotors: &[&str], reflector: &str) -> Result<Self, EnigmaError> {
+        let mut _rotors = Enigma::wheels(rotors, reflector)?;
 
-        if rotors.len() >= 5 || rotors.len() <= 2 {
-            panic!("{:?} Too many rotors, should be max 4 or min 3", rotors);
-        }
-
-        let len = rotors.len();
-        _rotors.rotors.clear();
+        Enigma::from(&mut _rotors, None, None, None)
+    }
 
-        if rotors.len() == 4 {}
+    /// Builds a machine from rotor and reflector names, leftmost wheel first.
+    /// Start positions and ring settings (Ringstellung) take one value per
+    /// wheel, so four for an M4 including its Greek wheel, written as letters
+    /// ("VJNA") or numbers ("02 21 12"). Both default to A for every wheel.
+    /// Where the reflector can be set, as on the commercial machines, the
+    /// start positions may lead with one more letter for it.
+    /// The model follows from the rotors: "d-i", "k-i", "swiss-k-i" and
+    /// "railway-i" through "-iii" build the commercial machines, "g-312-i"
+    /// and "g-260-i" the Enigma G and "t-i" to "t-viii" the Enigma T. These
+    /// take their own reflector ("d-ukw", "t-ukw" and so on) and no plug pairs.
+    /// Only the built-in wheels go by name here; `from_catalog` finds a
+    /// catalog's wheels as well.
+    pub fn from_config(
+        rotors: &[&str],
+        reflector: &str,
+        code_settings: Option<&str>,
+        ring_settings: Option<&str>,
+        plug_settings: Option<&[(char, char)]>,
+    ) -> Result<Self, EnigmaError> {
+        let mut _rotors = Enigma::wheels(rotors, reflector)?;
 
-        for x in rotors {
-            let mut beta_count = 2;
-            let mut gamma_count = 2;
-            match Rotor::from_str(x) {
-                Ok(r) => {
-                    let valid = vec![
-                        "i", "ii", "iii", "iv", "v", "vi", "vii", "viii", "beta", "gamma",
-                    ];
-
-                    if !valid.contains(&x) {
-                        panic!(
-                            "{:?} Rotor not valid, cant choose this rotor. Acceptable rotors: {:?}",
-                            x, valid
-                        );
-                    }
+        Enigma::from(&mut _rotors, code_settings, ring_settings, plug_settings)
+    }
 
-                    match r {
-                        Rotor::BETA => beta_count -= 1,
-                        Rotor::GAMMA => gamma_count -= 1,
-                        _ => (),
-                    }
+    /// Builds a machine as `from_config` does, looking the rotor and
+    /// reflector names up in `catalog` first and then among the built-ins.
+    pub fn from_catalog(
+        catalog: &Catalog,
+        rotors: &[&str],
+        reflector: &str,
+        code_settings: Option<&str>,
+        ring_settings: Option<&str>,
+        plug_settings: Option<&[(char, char)]>,
+    ) -> Result<Self, EnigmaError> {
+        let mut _rotors = catalog.rotors(rotors, reflector)?;
 
-                    if beta_count <= 0 || gamma_count <= 0 {
-                        panic!(
-                            "{:?} Beta or Gamma rotors cant be used more than twice",
-                            rotors
-                        );
-                    }
+        Enigma::from(&mut _rotors, code_settings, ring_settings, plug_settings)
+    }
 
-                    _rotors.rotors.push(Wheel::Rotor(r))
-                }
-                Err(_) => panic!("{:?} Rotor doesn't exist, try an existing one", x),
-            }
-        }
+    /// Builds a machine from wheels that are already picked out, leftmost
+    /// rotor first, such as one with a UKW-D wired up from the key sheet.
+    /// Settings work as for `from_config`.
+    pub fn from_rotors(
+        rotors: Rotors,
+        code_settings: Option<&str>,
+        ring_settings: Option<&str>,
+        plug_settings: Option<&[(char, char)]>,
+    ) -> Result<Self, EnigmaError> {
+        let mut _rotors = rotors;
 
-        match Reflector::from_str(reflector) {
-            Ok(x) => {
-                let mut valid = vec!["ukwb", "ukwc"];
-                if len == 3 {
-                    valid = vec!["ukwb", "ukwc", "bthin", "cthin"]
-                }
+        Enigma::from(&mut _rotors, code_settings, ring_settings, plug_settings)
+    }
 
-                if !valid.contains(&reflector) {
-                    panic!("{:?} Reflector not valid, cant choose this reflector for a {} numbered rotors. Acceptable reflectors: {:?}", rotors, len, valid);
-                }
+    fn wheels(rotors: &[&str], reflector: &str) -> Result<Rotors, EnigmaError> {
+        let mut _rotors = Rotors::default();
 
-                _rotors.reflector = x
-            }
-            Err(_) => panic!(
-                "{:?} Reflector doesn't exist, try an existing one",
-                reflector
-            ),
+        _rotors.rotors.clear();
+        for x in rotors {
+            let r = Rotor::from_str(x).map_err(|_| EnigmaError::UnknownRotor(x.to_string()))?;
+            _rotors.rotors.push(Wheel::Rotor(r))
         }
 
-        Enigma::from(&mut _rotors, None, None)
-    }
+        _rotors.reflector = Reflector::from_str(reflector)
+            .map_err(|_| EnigmaError::UnknownReflector(reflector.to_string()))?;
 
+        Ok(_rotors)
+    }
 
-    pub fn from_config(
-        rotors: &[&str],
-        reflector: &str,
-        code_settings: Option<[char; 3]>,
-        plug_settings: Option<&[(char, char)]>,
-    ) -> Self {
-        let mut _rotors = Rotors::default();
-        let len = rotors.len();
+    /// Checks a rotor order and reflector against what the machine they
+    /// belong to could physically take, and says which machine that is.
+    fn check(rotors: &Rotors) -> Result<Model, EnigmaError> {
+        let model = Model::of(&rotors.rotors)?;
+        let len = rotors.rotors.len();
 
-        if rotors.len() >= 5 || rotors.len() <= 2 {
-            panic!("{:?} Too many rotors, should be max 4 or min 3", rotors);
+        if len != model.rotors() {
+            return Err(EnigmaError::RotorCount {
+                given: len,
+                expected: model.rotors(),
+            });
         }
 
-        _rotors.rotors.clear();
-        for x in rotors {
-            match Rotor::from_str(x) {
-                Ok(r) => {
-                    let valid = vec![
-                        "i", "ii", "iii", "iv", "v", "vi", "vii", "viii", "beta", "gamma",
-                    ];
-
-                    if !valid.contains(&x) {
-                        panic!(
-                            "{:?} Rotor not valid, cant choose this rotor. Acceptable rotors: {:?}",
-                            x, valid
-                        );
-                    }
+        for (i, wheel) in rotors.rotors.iter().enumerate() {
+            let r = match wheel {
+                Wheel::Rotor(r) => r,
+                Wheel::Reflector(r) => return Err(EnigmaError::MisplacedRotor(r.to_string())),
+            };
 
-                    _rotors.rotors.push(Wheel::Rotor(r))
-                }
-                Err(_) => panic!("{:?} Rotor doesn't exist, try an existing one", x),
+            let greek = matches!(r, Rotor::BETA | Rotor::GAMMA);
+            let custom = matches!(r, Rotor::Custom(_));
+            if !custom && greek != (model == Model::M4 && i == 0) {
+                return Err(EnigmaError::MisplacedRotor(r.to_string()));
             }
-        }
 
-        match Reflector::from_str(reflector) {
-            Ok(x) => {
-                let mut valid = vec!["ukwb", "ukwc"];
-                if len == 4 {
-                    valid = vec!["ukwb", "ukwc", "bthin", "cthin"]
-                }
-
-                if !valid.contains(&reflector) {
-                    panic!("{:?} Reflector not valid, cant choose this reflector for a {} numbered rotors. Acceptable reflectors: {:?}", reflector, len, valid);
-                }
-
-                _rotors.reflector = x
+            if rotors.rotors[..i].contains(wheel) {
+                return Err(EnigmaError::DuplicateRotor(r.to_string()));
             }
-            Err(_) => panic!(
-                "{:?} Reflector doesn't exist, try an existing one",
-                reflector
-            ),
         }
 
-        Enigma::from(&mut _rotors, code_settings, plug_settings)
+        if !model.reflects(&rotors.reflector) {
+            return Err(EnigmaError::ReflectorNotAllowed {
+                reflector: rotors.reflector.to_string(),
+                model,
+            });
+        }
+
+        Ok(model)
     }
 
     fn from(
         config_wheel: &mut Rotors,
-        code_settings: Option<[char; 3]>,
+        code_settings: Option<&str>,
+        ring_settings: Option<&str>,
         plug_settings: Option<&[(char, char)]>,
-    ) -> Self {
-        let mut code = [0, 0, 0];
-        let mut plugboard: Stecker = zip(abc!(), abc!()).into_iter().clone().collect();
+    ) -> Result<Self, EnigmaError> {
+        let model = Enigma::check(config_wheel)?;
+        let len = config_wheel.rotors.len();
+        let mut code = [0; 5];
+        let mut rings = [0; 5];
+        let mut plugboard = Plugboard::new();
 
+        // settings are read leftmost wheel first, the circuit counts from the
+        // right, so a leading reflector position lands in the slot after the rotors
         if let Some(code_settings) = code_settings {
-            let mut index = 0;
-            for c in code_settings {
-                let alpha = abc!().iter().position(|ch| *ch == c as u32).unwrap();
+            match settings(code_settings) {
+                Some(c) if c.len() == len || (c.len() == len + 1 && model.settable_reflector()) => {
+                    for (index, alpha) in c.into_iter().rev().enumerate() {
+                        code[index] = alpha;
+                    }
+                }
+                _ => return Err(EnigmaError::InvalidPosition(code_settings.to_string())),
+            }
+        }
 
-                code[index] = alpha;
-                index += 1;
+        if let Some(ring_settings) = ring_settings {
+            match settings(ring_settings) {
+                Some(r) if r.len() == len => {
+                    for (index, ring) in r.into_iter().rev().enumerate() {
+                        rings[index] = ring;
+                    }
+                }
+                _ => return Err(EnigmaError::InvalidRing(ring_settings.to_string())),
             }
         }
 
-        let _ = &config_wheel.rotors.reverse();
-        let circuit = Circuit::new(&config_wheel.rotors, code);
+        if let Some(plug_settings) = plug_settings {
+            if !plug_settings.is_empty() && !model.plugboard() {
+                return Err(EnigmaError::NoPlugboard(model));
+            }
+
+            plugboard = Plugboard::from_pairs(plug_settings)?;
+        }
 
+        config_wheel.rotors.reverse();
         config_wheel
             .rotors
             .push(Wheel::Reflector(config_wheel.reflector.clone()));
 
-        if let Some(plug_settings) = plug_settings {
-            for i in plug_settings {
-                *plugboard.get_mut(&(i.0 as u32)).unwrap() = i.1 as u32;
-                *plugboard.get_mut(&(i.1 as u32)).unwrap() = i.0 as u32;
-            }
-        }
+        let entry = config_wheel.entry.clone().unwrap_or_else(|| model.entry());
+        let circuit = Circuit::new(
+            &config_wheel.rotors,
+            entry.alpha(),
+            model.drive(),
+            code,
+            rings,
+        );
 
-        // assert_eq!(plugboard, zip(abc!(), abc!()).into_iter().clone().collect());
-
-        Enigma {
+        Ok(Enigma {
             wheels: config_wheel.clone(),
+            model,
             circuit,
             plugboard,
-        }
+            uhr: None,
+            input: Input::default(),
+        })
     }
 
+    /// Enciphers `plaintext`, handling characters without a key as `input`
+    /// says. A substitute that isn't a letter itself is rejected the same way.
+    /// On an error the wheels are left where they started, so the text can
+    /// be cleaned up and sent again.
+    pub fn try_encode(&mut self, plaintext: &str) -> Result<String, EnigmaError> {
+        let state = self.snapshot();
+        let ciphertext = self.run(plaintext, self.input);
+        if ciphertext.is_err() {
+            self.restore(state);
+        }
+
+        ciphertext
+    }
 
+    /// Enciphers `plaintext` like `try_encode` but never fails: whatever the
+    /// input policy would reject is passed through unchanged instead.
     pub fn encode(&mut self, plaintext: &str) -> String {
-        let rotors = self.wheels.rotors.clone();
-        let etw = abc!();
+        let input = match self.input {
+            Input::Reject => Input::PassThrough,
+            Input::Substitute(c) if !c.is_ascii_alphabetic() => Input::PassThrough,
+            input => input,
+        };
+
+        self.run(plaintext, input)
+            .expect("a loosened input policy rejects nothing")
+    }
+
+    pub fn try_decode(&mut self, ciphertext: &str) -> Result<String, EnigmaError> {
+        self.try_encode(ciphertext)
+    }
+
+    pub fn decode(&mut self, ciphertext: &str) -> String {
+        self.encode(ciphertext)
+    }
+
+    pub fn to_json(&self) -> Result<String, EnigmaError> {
+        serde_json::to_string_pretty(self).map_err(|e| EnigmaError::InvalidConfig(e.to_string()))
+    }
+
+    pub fn from_json(s: &str) -> Result<Self, EnigmaError> {
+        let config: Config =
+            serde_json::from_str(s).map_err(|e| EnigmaError::InvalidConfig(e.to_string()))?;
+        Enigma::try_from(config)
+    }
+
+    pub fn to_toml(&self) -> Result<String, EnigmaError> {
+        toml::to_string(self).map_err(|e| EnigmaError::InvalidConfig(e.to_string()))
+    }
+
+    pub fn from_toml(s: &str) -> Result<Self, EnigmaError> {
+        let config: Config =
+            toml::from_str(s).map_err(|e| EnigmaError::InvalidConfig(e.to_string()))?;
+        Enigma::try_from(config)
+    }
+
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    pub fn plugboard(&self) -> &Plugboard {
+        &self.plugboard
+    }
+
+    /// Swaps the cables for `plugboard`'s. Only the models with a plugboard
+    /// take any.
+    pub fn set_plugboard(&mut self, plugboard: Plugboard) -> Result<(), EnigmaError> {
+        if !plugboard.pairs().is_empty() && !self.model.plugboard() {
+            return Err(EnigmaError::NoPlugboard(self.model));
+        }
+
+        self.plugboard = plugboard;
+        Ok(())
+    }
+
+    pub fn uhr(&self) -> Option<&Uhr> {
+        self.uhr.as_ref()
+    }
+
+    /// Plugs an Uhr in, or takes it out with `None`. Machines without a
+    /// plugboard have nowhere to plug one in.
+    pub fn set_uhr(&mut self, uhr: Option<Uhr>) -> Result<(), EnigmaError> {
+        if uhr.is_some() && !self.model.plugboard() {
+            return Err(EnigmaError::NoPlugboard(self.model));
+        }
+
+        self.uhr = uhr;
+        Ok(())
+    }
+
+    /// The letters showing in the windows, leftmost wheel first, led by the
+    /// reflector's on the machines where it can be set.
+    pub fn positions(&self) -> String {
+        self.windows(&self.circuit.code)
+    }
+
+    pub fn snapshot(&self) -> State {
+        State {
+            code: self.circuit.code,
+            step: self.circuit.step,
+        }
+    }
+
+    /// Turns the wheels back to a `snapshot`, to decipher from the same
+    /// point again or try another continuation.
+    pub fn restore(&mut self, state: State) {
+        self.circuit.code = state.code;
+        self.circuit.step = state.step;
+    }
+
+    /// Turns the wheels back to the start positions the machine was built
+    /// with.
+    pub fn reset(&mut self) {
+        self.circuit.code = self.circuit.start;
+        self.circuit.step = false;
+    }
+
+    /// Turns the wheels to where they'd be `presses` keypresses on from the
+    /// start positions, without stepping through them, to decipher from
+    /// the middle of a message or split a long one up. Only letters press a
+    /// key; whitespace and dropped characters don't count.
+    pub fn seek(&mut self, presses: usize) {
+        self.reset();
+        self.circuit.advance(presses);
+    }
+
+    /// The letters `seek(presses)` would show in the windows, leaving the
+    /// machine where it is.
+    pub fn position_after(&self, presses: usize) -> String {
+        let mut circuit = self.circuit.clone();
+        circuit.code = circuit.start;
+        circuit.step = false;
+        circuit.advance(presses);
+
+        self.windows(&circuit.code)
+    }
+
+    fn windows(&self, code: &[usize; 5]) -> String {
+        let len = match self.model.settable_reflector() {
+            true => self.circuit.len + 1,
+            false => self.circuit.len,
+        };
+        code[..len]
+            .iter()
+            .rev()
+            .map(|c| (*c as u8 + b'A') as char)
+            .collect()
+    }
+
+    fn run(&mut self, plaintext: &str, input: Input) -> Result<String, EnigmaError> {
+        let (inbound, outbound) = self.stecker();
+
+        let mut ciphertext = String::with_capacity(plaintext.len());
+        for (offset, key) in plaintext.char_indices() {
+            let mut ch = key.to_ascii_uppercase();
 
-        let binding = plaintext.to_uppercase();
-        let ciphertext = binding.chars().map(|mut ch| {
             // elimate unwanted chars
-            if ch.is_whitespace() {
-                return ch;
+            if key.is_whitespace() {
+                ciphertext.push(key);
+                continue;
+            }
+
+            if !ch.is_ascii_uppercase() {
+                ch = match input {
+                    Input::Reject => return Err(EnigmaError::InvalidInput { ch: key, offset }),
+                    Input::Drop => continue,
+                    Input::PassThrough => {
+                        ciphertext.push(key);
+                        continue;
+                    }
+                    Input::Substitute(c) if c.is_ascii_alphabetic() => c.to_ascii_uppercase(),
+                    Input::Substitute(_) => {
+                        return Err(EnigmaError::InvalidInput { ch: key, offset })
+                    }
+                };
             }
 
             self.circuit.accrue();
 
-            let ch_pos = &(etw.iter().position(|a| *a == ch as u32).unwrap() as u32);
-            ch = char::from_u32(self.plugboard[&(ch_pos + 65)] as u32)
-                .unwrap()
-                .clone();
+            let contact = inbound[(ch as u8 - b'A') as usize];
+            let contact = outbound[self.circuit.scramble(contact) as usize];
 
-            ch = self.circuit.scramble(&ch, &rotors);
-            ch = char::from_u32(
-                self.plugboard
-                    [&((self.circuit.signal()(ch as u32, self.circuit.code) + 65) as u32)],
-            )
-            .unwrap();
+            ciphertext.push((contact + b'A') as char);
+        }
 
-            self.circuit.reset();
+        Ok(ciphertext)
+    }
 
-            ch
-        });
+    /// The plugboard as contact tables: keys to the entry wheel, and the
+    /// entry wheel back to the lamps. These are the same unless an Uhr is in,
+    /// in which case any cable to one of its letters is left hanging.
+    fn stecker(&self) -> ([u8; 26], [u8; 26]) {
+        let plugboard = self.plugboard.wiring();
+        let uhr = match &self.uhr {
+            Some(uhr) => uhr.wiring(),
+            None => [None; 26],
+        };
 
-        ciphertext.into_iter().collect()
-    }
+        let mut inbound = [0; 26];
+        for (i, contact) in inbound.iter_mut().enumerate() {
+            let plugged = plugboard[i];
+            *contact = match uhr[i] {
+                Some(wired) => wired,
+                None if uhr[plugged as usize].is_some() => i as u8,
+                None => plugged,
+            };
+        }
 
+        let mut outbound = [0; 26];
+        for (i, contact) in inbound.iter().enumerate() {
+            outbound[*contact as usize] = i as u8;
+        }
 
-    pub fn decode(&mut self, ciphertext: &str) -> String {
-        self.encode(ciphertext)
+        (inbound, outbound)
     }
 }
 
 impl Default for Enigma {
     fn default() -> Self {
-        let plugboard: Stecker = zip(abc!(), abc!()).into_iter().clone().collect();
+        let plugboard = Plugboard::new();
+        let mut wheels = Rotors::default();
+
+        wheels.rotors.reverse();
+        wheels
+            .rotors
+            .push(Wheel::Reflector(wheels.reflector.clone()));
+
         Self {
-            wheels: Default::default(),
+            wheels,
+            model: Model::M3,
             circuit: Default::default(),
             plugboard,
+            uhr: None,
+            input: Default::default(),
         }
     }
 }
 
+/// A saved machine: the wheels leftmost first, ring settings as letters,
+/// and the window letters with the stepping latch for where it has got to.
+/// The start positions `reset` goes back to are the current ones if left out.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    model: Model,
+    rotors: Vec<Rotor>,
+    reflector: Reflector,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    entry: Option<Entry>,
+    rings: String,
+    #[serde(default)]
+    plugboard: Plugboard,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start: Option<String>,
+    positions: String,
+    #[serde(default)]
+    step: bool,
+    #[serde(default)]
+    input: Input,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uhr: Option<Uhr>,
+}
+
+impl From<Enigma> for Config {
+    fn from(x: Enigma) -> Self {
+        let len = x.circuit.len;
+        let rotors = x.wheels.rotors[..len]
+            .iter()
+            .rev()
+            .filter_map(|wheel| match wheel {
+                Wheel::Rotor(r) => Some(r.clone()),
+                Wheel::Reflector(_) => None,
+            })
+            .collect();
+        let rings = x.circuit.rings[..len]
+            .iter()
+            .rev()
+            .map(|r| (*r as u8 + b'A') as char)
+            .collect();
+
+        Config {
+            model: x.model,
+            rotors,
+            reflector: x.wheels.reflector.clone(),
+            entry: x.wheels.entry.clone(),
+            rings,
+            start: Some(x.windows(&x.circuit.start)),
+            positions: x.positions(),
+            step: x.circuit.step,
+            plugboard: x.plugboard,
+            input: x.input,
+            uhr: x.uhr,
+        }
+    }
+}
+
+impl TryFrom<Config> for Enigma {
+    type Error = EnigmaError;
+
+    fn try_from(x: Config) -> Result<Self, Self::Error> {
+        let wheels = Rotors {
+            reflector: x.reflector,
+            rotors: x.rotors.into_iter().map(Wheel::Rotor).collect(),
+            entry: x.entry,
+        };
+
+        let plugs = x.plugboard.pairs();
+        let start = x.start.as_deref().unwrap_or(&x.positions);
+        let mut enigma =
+            Enigma::from_rotors(wheels.clone(), Some(start), Some(&x.rings), Some(&plugs))?;
+        if start != x.positions {
+            enigma.circuit.code =
+                Enigma::from_rotors(wheels, Some(&x.positions), Some(&x.rings), Some(&plugs))?
+                    .circuit
+                    .code;
+        }
+
+        if enigma.model != x.model {
+            return Err(EnigmaError::InvalidConfig(format!(
+                "{:?} model given for {:?} rotors",
+                x.model.to_string(),
+                enigma.model.to_string()
+            )));
+        }
+
+        enigma.circuit.step = x.step;
+        enigma.set_uhr(x.uhr)?;
+        enigma.input = x.input;
+        Ok(enigma)
+    }
+}
+
 #[test]
 pub fn create_enigma() {
-    let plugboard: Stecker = zip(abc!(), abc!()).into_iter().clone().collect();
+    let plugboard = Plugboard::new();
 
     let enigma = Enigma {
         wheels: Rotors {
             reflector: Reflector::UKWB,
             rotors: vec![
-                Wheel::Rotor(Rotor::I),
-                Wheel::Rotor(Rotor::II),
                 Wheel::Rotor(Rotor::III),
+                Wheel::Rotor(Rotor::II),
+                Wheel::Rotor(Rotor::I),
+                Wheel::Reflector(Reflector::UKWB),
             ],
+            entry: None,
         },
+        model: Model::M3,
         circuit: Circuit {
-            code: [0, 0, 0],
-            node: Index {
-                front: 0,
-                back: 6 / 2 - 1,
-            },
+            code: [0; 5],
+            rings: [0; 5],
             step: false,
-            len: 4,
-            turnovers: vec!["Q".to_string(), "E".to_string(), "V".to_string()],
+            len: 3,
+            // notches at V, E and Q
+            turnovers: [1 << 21, 1 << 4, 1 << 16, 0],
+            ..Default::default()
         },
         plugboard,
+        uhr: None,
+        input: Input::Reject,
     };
 
     assert_eq!(enigma, Enigma::new());
+    assert_eq!(Enigma::new().encode("AAAAA"), "BDZGO");
 }
 
 #[test]
 pub fn create_enigma_w_config() {
-    let plugboard: Stecker = zip(abc!(), abc!()).into_iter().clone().collect();
+    let plugboard = Plugboard::new();
 
     let enigma = Enigma {
         wheels: Rotors {
@@ -277,25 +612,28 @@ pub fn create_enigma_w_config() {
                 Wheel::Rotor(Rotor::III),
                 Wheel::Rotor(Rotor::II),
                 Wheel::Rotor(Rotor::I),
-                Wheel::Reflector(Reflector::UKWB)
+                Wheel::Reflector(Reflector::UKWB),
             ],
+            entry: None,
         },
+        model: Model::M3,
         circuit: Circuit {
-            code: [0, 0, 0],
-            node: Index {
-                front: 0,
-                back: 6 / 2 - 1,
-            },
+            code: [0; 5],
+            rings: [0; 5],
             step: false,
-            len: 4,
-            turnovers: vec!["V".to_string(), "E".to_string(), "Q".to_string()],
+            len: 3,
+            // notches at V, E and Q
+            turnovers: [1 << 21, 1 << 4, 1 << 16, 0],
+            ..Default::default()
         },
         plugboard,
+        uhr: None,
+        input: Input::Reject,
     };
 
     assert_eq!(
         enigma,
-        Enigma::from_config(&["i", "ii", "iii"], "ukwb", None, None)
+        Enigma::from_config(&["i", "ii", "iii"], "ukwb", None, None, None).unwrap()
     );
 }
 
@@ -304,7 +642,751 @@ pub fn create_enigma_w_config_w_m4m3combos() {
     let plugboard = [('X', 'S'), ('F', 'V')];
     // let code = Some(['Q', 'E', 'V']);
 
-    let mut enigma = Enigma::from_config(&["i", "ii", "iii"], "ukwc", None, Some(&plugboard));
+    let mut enigma =
+        Enigma::from_config(&["i", "ii", "iii"], "ukwc", None, None, Some(&plugboard)).unwrap();
+
+    assert_eq!(
+        enigma.encode("EPAUK PEDJERBYLE HIIXJ MQVLJDNCYF BYLUZ QMVGOVQXXC JFGOF CWJEEMPWUZ GFWLF ZMUBHZRWXQ GDAWH KCIAUIJSWO CGSRH NRFKQPDHLT QYLBE FRGMLQEJMB NOUYD JBYITCVNMM KGGIP VYMVGSCFHN"),
+        "URBAN OUTFITTERS ".repeat(10).trim_end()
+    );
+}
+
+#[test]
+pub fn create_enigma_w_config_w_rings() {
+    let mut enigma =
+        Enigma::from_config(&["i", "ii", "iii"], "ukwb", None, Some("BBB"), None).unwrap();
+    assert_eq!(enigma.encode("AAAAA"), "EWTYX");
+
+    let mut enigma =
+        Enigma::from_config(&["i", "ii", "iii"], "ukwb", None, Some("02 02 02"), None).unwrap();
+    assert_eq!(enigma.decode("EWTYX"), "AAAAA");
+}
+
+#[test]
+pub fn create_enigma_w_config_w_m4_greek_wheel() {
+    let mut m3 = Enigma::from_config(&["i", "ii", "iii"], "ukwb", Some("XYZ"), None, None).unwrap();
+    // beta with its window matching its ring lines up with the thin reflector as UKW-B
+    let mut m4 = Enigma::from_config(
+        &["beta", "i", "ii", "iii"],
+        "bthin",
+        Some("QXYZ"),
+        Some("QAAA"),
+        None,
+    )
+    .unwrap();
+
+    let plaintext = "VONVONJLOOKSJHFFTTTEINSEINSDREIZWOYYQNNSNEUNINHALTXXBEIANGRIFF";
+    assert_eq!(m3.encode(plaintext), m4.encode(plaintext));
+    assert_eq!(m4.positions(), "QXAJ");
+
+    let mut m4 = Enigma::from_config(
+        &["beta", "i", "ii", "iii"],
+        "bthin",
+        Some("VJNA"),
+        None,
+        None,
+    )
+    .unwrap();
+    let ciphertext = m4.encode(plaintext);
+    assert_eq!(m4.circuit.code[3], 21);
+
+    let mut m4 = Enigma::from_config(
+        &["beta", "i", "ii", "iii"],
+        "bthin",
+        Some("AJNA"),
+        None,
+        None,
+    )
+    .unwrap();
+    assert_ne!(m4.encode(plaintext), ciphertext);
+
+    let mut m4 = Enigma::from_config(
+        &["beta", "i", "ii", "iii"],
+        "bthin",
+        Some("VJNA"),
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(m4.decode(&ciphertext), plaintext);
+}
+
+#[test]
+pub fn create_enigma_w_bad_config() {
+    assert_eq!(
+        Enigma::from_config(&["i", "ii", "ix"], "ukwb", None, None, None),
+        Err(EnigmaError::UnknownRotor("ix".to_string()))
+    );
+    assert_eq!(
+        Enigma::from_config(&["i", "ii", "i"], "ukwb", None, None, None),
+        Err(EnigmaError::DuplicateRotor("i".to_string()))
+    );
+    assert_eq!(
+        Enigma::from_config(&["i", "beta", "iii"], "ukwb", None, None, None),
+        Err(EnigmaError::MisplacedRotor("beta".to_string()))
+    );
+    assert_eq!(
+        Enigma::from_wheels(&["i", "ii"], "ukwb"),
+        Err(EnigmaError::RotorCount {
+            given: 2,
+            expected: 3
+        })
+    );
+    assert_eq!(
+        Enigma::from_wheels(&["i", "ii", "iii"], "ukwa"),
+        Err(EnigmaError::UnknownReflector("ukwa".to_string()))
+    );
+    assert_eq!(
+        Enigma::from_wheels(&["i", "ii", "iii"], "bthin"),
+        Err(EnigmaError::ReflectorNotAllowed {
+            reflector: "bthin".to_string(),
+            model: Model::M3
+        })
+    );
+    assert_eq!(
+        Enigma::from_config(&["i", "ii", "iii"], "ukwb", Some("A1"), None, None),
+        Err(EnigmaError::InvalidPosition("A1".to_string()))
+    );
+    assert_eq!(
+        Enigma::from_config(&["i", "ii", "iii"], "ukwb", None, Some("01 02"), None),
+        Err(EnigmaError::InvalidRing("01 02".to_string()))
+    );
+    assert_eq!(
+        Enigma::from_config(&["i", "ii", "iii"], "ukwb", None, Some("1A2B3C"), None),
+        Err(EnigmaError::InvalidRing("1A2B3C".to_string()))
+    );
+    assert_eq!(
+        Enigma::from_config(&["i", "ii", "iii"], "ukwb", Some("X1 Y2 Z3"), None, None),
+        Err(EnigmaError::InvalidPosition("X1 Y2 Z3".to_string()))
+    );
+    assert_eq!(
+        Enigma::from_config(&["i", "ii", "iii"], "ukwb", None, None, Some(&[('A', '1')])),
+        Err(EnigmaError::InvalidPlugPair('A', '1'))
+    );
+    assert_eq!(
+        Enigma::from_config(
+            &["i", "ii", "iii"],
+            "ukwb",
+            None,
+            None,
+            Some(&[('a', 'b'), ('A', 'C')])
+        ),
+        Err(EnigmaError::DuplicatePlug('A'))
+    );
+    assert_eq!(
+        Enigma::from_config(&["i", "ii", "iii"], "ukwb", None, None, Some(&[('Q', 'q')])),
+        Err(EnigmaError::InvalidPlugPair('Q', 'q'))
+    );
+}
+
+#[test]
+pub fn encode_w_input_policies() {
+    let mut enigma = Enigma::from_config(&["i", "ii", "iii"], "ukwb", None, None, None).unwrap();
+    assert_eq!(
+        enigma.try_encode("aaa 1aa"),
+        Err(EnigmaError::InvalidInput { ch: '1', offset: 4 })
+    );
+    // nothing moved, so the cleaned up text enciphers as if sent first
+    assert_eq!(enigma.positions(), "AAA");
+    assert!(!enigma.circuit.step);
+    assert_eq!(enigma.try_encode("aaa aa"), Ok("BDZ GO".to_string()));
+
+    let mut enigma = Enigma::from_config(&["i", "ii", "iii"], "ukwb", None, None, None).unwrap();
+    assert_eq!(
+        enigma.try_encode("äaa"),
+        Err(EnigmaError::InvalidInput {
+            ch: 'ä', offset: 0
+        })
+    );
+
+    let mut enigma = Enigma::from_config(&["i", "ii", "iii"], "ukwb", None, None, None).unwrap();
+    assert_eq!(enigma.encode("aa, a🙂aa!"), "BD, Z🙂GO!");
+
+    let mut enigma = Enigma::from_config(&["i", "ii", "iii"], "ukwb", None, None, None).unwrap();
+    enigma.input = Input::Drop;
+    assert_eq!(enigma.try_encode("aa, a🙂aa!"), Ok("BD ZGO".to_string()));
+
+    let mut enigma = Enigma::from_config(&["i", "ii", "iii"], "ukwb", None, None, None).unwrap();
+    enigma.input = Input::Substitute('x');
+    assert_eq!(
+        enigma.try_encode("A1"),
+        Ok(
+            Enigma::from_config(&["i", "ii", "iii"], "ukwb", None, None, None)
+                .unwrap()
+                .encode("AX")
+        )
+    );
+
+    let mut enigma = Enigma::from_config(&["i", "ii", "iii"], "ukwb", None, None, None).unwrap();
+    enigma.input = Input::Substitute('?');
+    assert_eq!(
+        enigma.try_encode("A1"),
+        Err(EnigmaError::InvalidInput { ch: '1', offset: 1 })
+    );
+    assert_eq!(enigma.positions(), "AAA");
+
+    let mut enigma = Enigma::from_config(&["i", "ii", "iii"], "ukwb", None, None, None).unwrap();
+    enigma.input = Input::Substitute('?');
+    assert_eq!(enigma.encode("A1"), "B1");
+}
+
+#[test]
+pub fn create_enigma_w_ukwd() {
+    let ukwd = Reflector::ukwd(
+        "AZ CY DX EW FV GU HT IS JR KQ LP MN",
+        crate::rotors::Notation::Bletchley,
+    )
+    .unwrap();
+
+    let rotors = Rotors {
+        reflector: ukwd.clone(),
+        rotors: vec![
+            Wheel::Rotor(Rotor::V),
+            Wheel::Rotor(Rotor::I),
+            Wheel::Rotor(Rotor::IV),
+        ],
+        entry: None,
+    };
+
+    let plugboard = [('A', 'V'), ('B', 'S')];
+    let mut enigma =
+        Enigma::from_rotors(rotors.clone(), Some("QRS"), Some("BUL"), Some(&plugboard)).unwrap();
+    let ciphertext = enigma.encode("LUFTWAFFE");
+    assert_ne!(ciphertext, "LUFTWAFFE");
+
+    let mut enigma =
+        Enigma::from_rotors(rotors, Some("QRS"), Some("BUL"), Some(&plugboard)).unwrap();
+    assert_eq!(enigma.decode(&ciphertext), "LUFTWAFFE");
+
+    let rotors = Rotors {
+        reflector: ukwd,
+        rotors: vec![
+            Wheel::Rotor(Rotor::BETA),
+            Wheel::Rotor(Rotor::V),
+            Wheel::Rotor(Rotor::I),
+            Wheel::Rotor(Rotor::IV),
+        ],
+        entry: None,
+    };
+    assert_eq!(
+        Enigma::from_rotors(rotors, None, None, None),
+        Err(EnigmaError::ReflectorNotAllowed {
+            reflector: "ukwd".to_string(),
+            model: Model::M4
+        })
+    );
+}
+
+#[test]
+pub fn create_commercial_enigma() {
+    // "HWQIO", "OMZRN" and "KLKMZTGLMSBFR" came out of this crate and
+    // haven't been checked against a published message or another
+    // simulator, so they guard against changes rather than prove the
+    // commercial wirings right.
+    let mut d = Enigma::from_config(&["d-i", "d-ii", "d-iii"], "d-ukw", None, None, None).unwrap();
+    assert_eq!(d.model(), Model::D);
+    assert_eq!(d.encode("AAAAA"), "HWQIO");
+    assert_eq!(d.positions(), "AAAF");
+
+    // the reflector set to K
+    let mut d =
+        Enigma::from_config(&["d-i", "d-ii", "d-iii"], "d-ukw", Some("KAAA"), None, None).unwrap();
+    assert_eq!(d.encode("AAAAA"), "OMZRN");
+
+    let mut railway = Enigma::from_config(
+        &["railway-i", "railway-ii", "railway-iii"],
+        "railway-ukw",
+        Some("ZQRS"),
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(railway.encode("RAILWAYENIGMA"), "KLKMZTGLMSBFR");
+
+    let mut swiss = Enigma::from_config(
+        &["swiss-k-iii", "swiss-k-i", "swiss-k-ii"],
+        "swiss-k-ukw",
+        Some("BXYZ"),
+        Some("CDE"),
+        Some(&[]),
+    )
+    .unwrap();
+    let ciphertext = swiss.encode("BERNBERN");
+    let mut swiss = Enigma::from_config(
+        &["swiss-k-iii", "swiss-k-i", "swiss-k-ii"],
+        "swiss-k-ukw",
+        Some("BXYZ"),
+        Some("CDE"),
+        None,
+    )
+    .unwrap();
+    assert_eq!(swiss.decode(&ciphertext), "BERNBERN");
+}
+
+#[test]
+pub fn create_commercial_enigma_w_bad_config() {
+    assert_eq!(
+        Enigma::from_config(&["d-i", "d-ii", "iii"], "d-ukw", None, None, None),
+        Err(EnigmaError::MixedModels("iii".to_string()))
+    );
+    assert_eq!(
+        Enigma::from_config(&["k-i", "k-ii", "k-iii"], "d-ukw", None, None, None),
+        Err(EnigmaError::ReflectorNotAllowed {
+            reflector: "d-ukw".to_string(),
+            model: Model::K
+        })
+    );
+    assert_eq!(
+        Enigma::from_config(
+            &["k-i", "k-ii", "k-iii"],
+            "k-ukw",
+            None,
+            None,
+            Some(&[('A', 'B')])
+        ),
+        Err(EnigmaError::NoPlugboard(Model::K))
+    );
+    let mut k = Enigma::from_config(&["k-i", "k-ii", "k-iii"], "k-ukw", None, None, None).unwrap();
+    assert_eq!(
+        k.set_plugboard("AB CD".parse().unwrap()),
+        Err(EnigmaError::NoPlugboard(Model::K))
+    );
+    assert_eq!(k.set_plugboard(Plugboard::new()), Ok(()));
+    assert_eq!(
+        k.encode("AAAAA"),
+        Enigma::from_config(&["k-i", "k-ii", "k-iii"], "k-ukw", None, None, None)
+            .unwrap()
+            .encode("AAAAA")
+    );
+    assert_eq!(
+        Enigma::from_config(&["d-i", "d-ii", "d-iii", "d-i"], "d-ukw", None, None, None),
+        Err(EnigmaError::RotorCount {
+            given: 4,
+            expected: 3
+        })
+    );
+    assert_eq!(
+        EnigmaError::RotorCount {
+            given: 4,
+            expected: 3
+        }
+        .to_string(),
+        "4 rotors given, the machine takes 3"
+    );
+    // only the commercial reflectors take a position
+    assert_eq!(
+        Enigma::from_config(&["i", "ii", "iii"], "ukwb", Some("KAAA"), None, None),
+        Err(EnigmaError::InvalidPosition("KAAA".to_string()))
+    );
+}
+
+#[test]
+pub fn create_enigma_g() {
+    // No published G-312 or G-260 message with its key is to hand, so the
+    // ciphertexts below are this crate's own output, kept to catch changes.
+    // They are unverified against a real machine or a reference simulator;
+    // the stepping and the positions are what these checks stand on.
+    let mut g = Enigma::from_config(
+        &["g-312-i", "g-312-ii", "g-312-iii"],
+        "g-312-ukw",
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(g.model(), Model::G312);
+    assert_eq!(g.encode("AAAAAAAAAA"), "EBTMYLCEYW");
+    assert_eq!(g.positions(), "DDEK");
+
+    let mut g = Enigma::from_config(
+        &["g-312-iii", "g-312-i", "g-312-ii"],
+        "g-312-ukw",
+        Some("HZQP"),
+        Some("FGH"),
+        None,
+    )
+    .unwrap();
+    assert_eq!(g.encode("ABWEHRNACHRICHT"), "GUAJWMBGEDAGHRN");
+    assert_eq!(g.positions(), "IEZE");
+
+    // every rotor on a notch carries all the way into the reflector
+    let mut g = Enigma::from_config(
+        &["g-312-i", "g-312-ii", "g-312-iii"],
+        "g-312-ukw",
+        Some("MQSU"),
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(g.encode("X"), "N");
+    assert_eq!(g.positions(), "NRTV");
+
+    let mut g = Enigma::from_config(
+        &["g-260-i", "g-260-ii", "g-260-iii"],
+        "g-260-ukw",
+        Some("BCDE"),
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(g.decode("YKCLVJHVQZY"), "BUENOSAIRES");
+}
+
+#[test]
+pub fn create_enigma_t() {
+    let mut t =
+        Enigma::from_config(&["t-vii", "t-i", "t-iv"], "t-ukw", Some("QWVY"), None, None).unwrap();
+    assert_eq!(t.model(), Model::T);
+    // unchecked against any published Enigma T traffic or simulator: this
+    // only holds the Tirpitz wirings and five notch stepping where they are
+    assert_eq!(t.encode("TOKIOTOKIOTOKIO"), "DJQOLZANXAQFFLW");
+
+    assert_eq!(
+        Enigma::from_config(&["t-vii", "i", "t-iv"], "t-ukw", None, None, None),
+        Err(EnigmaError::MixedModels("i".to_string()))
+    );
+    assert_eq!(
+        Enigma::from_config(&["t-vii", "t-i", "t-iv"], "ukwb", None, None, None),
+        Err(EnigmaError::ReflectorNotAllowed {
+            reflector: "ukwb".to_string(),
+            model: Model::T
+        })
+    );
+}
+
+#[test]
+pub fn create_enigma_w_entry() {
+    use crate::rotors::Entry;
+
+    let wheels = Rotors {
+        entry: Some(Entry::QWERTZU),
+        ..Default::default()
+    };
+    let mut enigma = Enigma::from_rotors(wheels, None, None, None).unwrap();
+    assert_eq!(enigma.encode("AAAAA"), "YWRLJ");
+
+    let d = |entry| Rotors {
+        reflector: Reflector::DUKW,
+        rotors: vec![
+            Wheel::Rotor(Rotor::DI),
+            Wheel::Rotor(Rotor::DII),
+            Wheel::Rotor(Rotor::DIII),
+        ],
+        entry,
+    };
+    let mut enigma = Enigma::from_rotors(d(Some(Entry::Identity)), None, None, None).unwrap();
+    assert_eq!(enigma.encode("AAAAA"), "QDJYD");
+    let mut enigma = Enigma::from_rotors(d(Some(Entry::QWERTZU)), None, None, None).unwrap();
+    assert_eq!(enigma.encode("AAAAA"), "HWQIO");
+
+    // a custom entry wheel is undone on the way back out
+    let entry = Entry::custom("etw", "ZYXWVUTSRQPONMLKJIHGFEDCBA").unwrap();
+    let mut enigma = Enigma::from_rotors(d(Some(entry.clone())), None, None, None).unwrap();
+    let ciphertext = enigma.encode("EINTRITTSWALZE");
+    let mut enigma = Enigma::from_rotors(d(Some(entry)), None, None, None).unwrap();
+    assert_eq!(enigma.decode(&ciphertext), "EINTRITTSWALZE");
+
+    assert_eq!(
+        Entry::custom("etw", "ABCDEFGHIJKLMNOPQRSTUVWXYA"),
+        Err(EnigmaError::InvalidWiring("etw".to_string()))
+    );
+}
+
+#[test]
+pub fn encode_w_uhr() {
+    let pairs = [
+        ('A', 'W'),
+        ('B', 'X'),
+        ('C', 'Y'),
+        ('D', 'Z'),
+        ('E', 'V'),
+        ('F', 'U'),
+        ('G', 'T'),
+        ('H', 'S'),
+        ('I', 'R'),
+        ('J', 'Q'),
+    ];
+    let plaintext = "UHRBOXEINSATZBEREIT";
+
+    // at 00 the Uhr is ten ordinary cables
+    let mut cables =
+        Enigma::from_config(&["iv", "ii", "v"], "ukwb", Some("KDO"), None, Some(&pairs)).unwrap();
+    let mut enigma =
+        Enigma::from_config(&["iv", "ii", "v"], "ukwb", Some("KDO"), None, None).unwrap();
+    enigma.set_uhr(Some(Uhr::new(&pairs, 0).unwrap())).unwrap();
+    assert_eq!(enigma.encode(plaintext), cables.encode(plaintext));
+
+    let mut enigma =
+        Enigma::from_config(&["iv", "ii", "v"], "ukwb", Some("KDO"), None, None).unwrap();
+    enigma.set_uhr(Some(Uhr::new(&pairs, 27).unwrap())).unwrap();
+    let ciphertext = enigma.encode(plaintext);
+    assert_ne!(ciphertext, cables.clone().encode(plaintext));
+
+    let mut enigma =
+        Enigma::from_config(&["iv", "ii", "v"], "ukwb", Some("KDO"), None, None).unwrap();
+    enigma.set_uhr(Some(Uhr::new(&pairs, 27).unwrap())).unwrap();
+    assert_eq!(enigma.decode(&ciphertext), plaintext);
+
+    // K isn't on the Uhr, so only the lamp it lights goes back through it
+    let mut bare =
+        Enigma::from_config(&["iv", "ii", "v"], "ukwb", Some("KDO"), None, None).unwrap();
+    let mut enigma = bare.clone();
+    enigma.set_uhr(Some(Uhr::new(&pairs, 27).unwrap())).unwrap();
+    let through = bare.encode("K").as_bytes()[0];
+    let lamp = b"WXVRTZUSQYKLMNOPBJAHEGDICF"
+        .iter()
+        .position(|c| *c == through)
+        .unwrap();
+    assert_eq!(
+        enigma.encode("K"),
+        ((lamp as u8 + b'A') as char).to_string()
+    );
+
+    // a cable to one of the Uhr's letters is left hanging
+    let mut enigma =
+        Enigma::from_config(&["iv", "ii", "v"], "ukwb", Some("KDO"), None, None).unwrap();
+    enigma.set_plugboard("AK LM".parse().unwrap()).unwrap();
+    enigma.set_uhr(Some(Uhr::new(&pairs, 13).unwrap())).unwrap();
+    let ciphertext = enigma.clone().encode(plaintext);
+    let mut hanging = enigma.clone();
+    hanging.set_plugboard("LM".parse().unwrap()).unwrap();
+    assert_eq!(hanging.encode(plaintext), ciphertext);
+    assert_eq!(enigma.decode(&ciphertext), plaintext);
+
+    // nothing to plug it into on a model without a plugboard
+    let mut enigma =
+        Enigma::from_config(&["k-i", "k-ii", "k-iii"], "k-ukw", None, None, None).unwrap();
+    assert_eq!(
+        enigma.set_uhr(Some(Uhr::new(&pairs, 27).unwrap())),
+        Err(EnigmaError::NoPlugboard(Model::K))
+    );
+    assert_eq!(enigma.uhr(), None);
+
+    // nor can a saved one be loaded with it
+    let mut json: serde_json::Value = serde_json::from_str(&enigma.to_json().unwrap()).unwrap();
+    json["uhr"] = serde_json::json!({ "pairs": "AW BX CY DZ EV FU GT HS IR JQ", "setting": 27 });
+    assert_eq!(
+        Enigma::from_json(&json.to_string()),
+        Err(EnigmaError::NoPlugboard(Model::K))
+    );
+}
+
+#[test]
+pub fn save_enigma_mid_message() {
+    let pairs = [
+        ('A', 'W'),
+        ('B', 'X'),
+        ('C', 'Y'),
+        ('D', 'Z'),
+        ('E', 'V'),
+        ('F', 'U'),
+        ('G', 'T'),
+        ('H', 'S'),
+        ('I', 'R'),
+        ('J', 'Q'),
+    ];
+    let mut enigma = Enigma::from_config(
+        &["i", "ii", "iii"],
+        "ukwc",
+        Some("ADT"),
+        Some("02 21 12"),
+        Some(&[('K', 'L'), ('M', 'N')]),
+    )
+    .unwrap();
+    enigma.set_uhr(Some(Uhr::new(&pairs, 27).unwrap())).unwrap();
+    enigma.input = Input::Substitute('X');
+
+    // stop right after the middle rotor's double step, latch set
+    enigma.encode("AAAA");
+    assert!(enigma.circuit.step);
+    assert_eq!(enigma.positions(), "BFX");
+
+    let json = enigma.to_json().unwrap();
+    assert!(json.contains(r#""positions": "BFX""#), "{}", json);
+    assert!(json.contains(r#""step": true"#), "{}", json);
+    let mut restored = Enigma::from_json(&json).unwrap();
+    assert_eq!(restored, enigma);
+    assert_eq!(
+        restored.encode("MID MESSAGE?"),
+        enigma.clone().encode("MID MESSAGE?")
+    );
+
+    let toml = enigma.to_toml().unwrap();
+    assert!(toml.contains(r#"rings = "BUL""#), "{}", toml);
+    assert_eq!(Enigma::from_toml(&toml), Ok(enigma));
+
+    // made to order wheels go with their wiring, and a settable reflector's
+    // position with the rest
+    let ukwd = Reflector::ukwd(
+        "AZ CY DX EW FV GU HT IS JR KQ LP MN",
+        crate::rotors::Notation::Bletchley,
+    )
+    .unwrap();
+    let rotors = Rotors {
+        reflector: ukwd,
+        rotors: vec![
+            Wheel::Rotor(Rotor::custom("x-i", "EKMFLGDQVZNTOWYHXUSPAIBRCJ", "Q").unwrap()),
+            Wheel::Rotor(Rotor::I),
+            Wheel::Rotor(Rotor::IV),
+        ],
+        entry: Some(Entry::custom("x-etw", "QWERTZUIOASDFGHJKPYXCVBNML").unwrap()),
+    };
+    let mut enigma = Enigma::from_rotors(rotors, Some("QRS"), None, None).unwrap();
+    enigma.encode("LUFTWAFFE");
+    assert_eq!(
+        Enigma::from_toml(&enigma.to_toml().unwrap()),
+        Ok(enigma.clone())
+    );
+    assert_eq!(Enigma::from_json(&enigma.to_json().unwrap()), Ok(enigma));
+
+    // a made to order reflector that happens to be called "ukwd" stays one
+    let rotors = Rotors {
+        reflector: Reflector::custom("ukwd", "YRUHQSLDPXNGOKMIEBFZCWVJAT").unwrap(),
+        rotors: vec![
+            Wheel::Rotor(Rotor::II),
+            Wheel::Rotor(Rotor::I),
+            Wheel::Rotor(Rotor::IV),
+        ],
+        entry: None,
+    };
+    let enigma = Enigma::from_rotors(rotors, None, None, None).unwrap();
+    assert_eq!(
+        Enigma::from_json(&enigma.to_json().unwrap()),
+        Ok(enigma.clone())
+    );
+    assert_eq!(Enigma::from_toml(&enigma.to_toml().unwrap()), Ok(enigma));
+
+    let mut enigma = Enigma::from_config(
+        &["g-312-i", "g-312-ii", "g-312-iii"],
+        "g-312-ukw",
+        Some("QZZZ"),
+        None,
+        None,
+    )
+    .unwrap();
+    enigma.encode("ABWEHR");
+    let restored = Enigma::from_json(&enigma.to_json().unwrap()).unwrap();
+    assert_eq!(restored.positions(), enigma.positions());
+    assert_eq!(restored, enigma);
+
+    // a model without a plugboard can't be given cables, so whatever it
+    // saves loads back
+    let mut k = Enigma::from_config(
+        &["k-iii", "k-i", "k-ii"],
+        "k-ukw",
+        Some("QEVZ"),
+        Some("05 11 21"),
+        None,
+    )
+    .unwrap();
+    assert!(k.set_plugboard("AB CD".parse().unwrap()).is_err());
+    k.encode("SCHWEIZ");
+    assert_eq!(Enigma::from_json(&k.to_json().unwrap()), Ok(k.clone()));
+    assert_eq!(Enigma::from_toml(&k.to_toml().unwrap()), Ok(k));
+
+    let config = |model: &str, rotor: &str| {
+        format!(
+            r#"{{"model": "{}", "rotors": ["{}", "ii", "iii"], "reflector": "ukwb",
+                "rings": "AAA", "positions": "AAA"}}"#,
+            model, rotor
+        )
+    };
+    assert!(Enigma::from_json(&config("m3", "i")).is_ok());
+    assert_eq!(
+        Enigma::from_json(&config("m4", "i")),
+        Err(EnigmaError::InvalidConfig(
+            "\"m4\" model given for \"m3\" rotors".to_string()
+        ))
+    );
+    assert!(matches!(
+        Enigma::from_json(&config("m3", "ix")),
+        Err(EnigmaError::InvalidConfig(_))
+    ));
+    assert!(matches!(
+        Enigma::from_json(&config("m5", "i")),
+        Err(EnigmaError::InvalidConfig(_))
+    ));
+}
+
+#[test]
+pub fn snapshot_and_reset() {
+    let mut enigma = Enigma::from_config(
+        &["i", "ii", "iii"],
+        "ukwb",
+        Some("ADT"),
+        Some("BUL"),
+        Some(&[('A', 'V'), ('B', 'S')]),
+    )
+    .unwrap();
+    let ciphertext = enigma.encode("WETTERBERICHT");
+
+    enigma.reset();
+    assert_eq!(enigma.positions(), "ADT");
+    assert!(!enigma.circuit.step);
+    assert_eq!(enigma.decode(&ciphertext), "WETTERBERICHT");
+
+    // through the double step, with the latch set, and on from there twice
+    enigma.reset();
+    enigma.encode("AAAA");
+    let state = enigma.snapshot();
+    assert!(enigma.circuit.step);
+    let first = enigma.encode("FORTSETZUNG");
+    assert_ne!(enigma.snapshot(), state);
+
+    enigma.restore(state);
+    assert_eq!(enigma.positions(), "BFX");
+    assert_eq!(enigma.encode("FORTSETZUNG"), first);
+
+    // a saved machine goes back to where it first started
+    enigma.restore(state);
+    let mut restored = Enigma::from_json(&enigma.to_json().unwrap()).unwrap();
+    assert_eq!(restored, enigma);
+    restored.reset();
+    assert_eq!(restored.positions(), "ADT");
+    assert_eq!(restored.decode(&ciphertext), "WETTERBERICHT");
+
+    let mut enigma = Enigma::from_config(
+        &["g-312-i", "g-312-ii", "g-312-iii"],
+        "g-312-ukw",
+        Some("QZZZ"),
+        None,
+        None,
+    )
+    .unwrap();
+    enigma.encode("ABWEHR");
+    enigma.reset();
+    assert_eq!(enigma.positions(), "QZZZ");
+}
+
+#[test]
+pub fn seek_into_message() {
+    let mut enigma = Enigma::from_config(
+        &["beta", "vi", "ii", "viii"],
+        "cthin",
+        Some("RZDY"),
+        Some("AEQL"),
+        Some(&[('A', 'T'), ('B', 'L')]),
+    )
+    .unwrap();
+    let plaintext = "DASOBERKOMMANDODERWEHRMACHTGIBTBEKANNT".repeat(40);
+    let ciphertext = enigma.encode(&plaintext);
+
+    for offset in [0, 1, 25, 26, 650, 677, 1000, plaintext.len()] {
+        enigma.seek(offset);
+        assert_eq!(enigma.decode(&ciphertext[offset..]), plaintext[offset..]);
+    }
+
+    // the machine is where it stopped, and stays there
+    assert_eq!(enigma.position_after(plaintext.len()), enigma.positions());
+    enigma.seek(3);
+    assert_eq!(enigma.position_after(0), "RZDY");
+    assert_eq!(enigma.position_after(1), "RZDZ");
+    assert_eq!(enigma.positions(), enigma.position_after(3));
 
-    println!("{}", enigma.encode("EPAUK PEDJERBYLE HIIXJ MQVLJDNCYF BYLUZ QMVGOVQXXC JFGOF CWJEEMPWUZ GFWLF ZMUBHZRWXQ GDAWH KCIAUIJSWO CGSRH NRFKQPDHLT QYLBE FRGMLQEJMB NOUYD JBYITCVNMM KGGIP VYMVGSCFHN"))
+    let mut stepped = enigma.clone();
+    stepped.reset();
+    stepped.encode(&"A".repeat(123_456));
+    assert_eq!(enigma.position_after(123_456), stepped.positions());
+    enigma.seek(123_456);
+    assert_eq!(enigma.snapshot(), stepped.snapshot());
 }
@@ -0,0 +1,200 @@
+use std::fmt;
+
+use crate::model::Model;
+
+/// Reasons a machine can't be put together from the settings it was given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnigmaError {
+    UnknownRotor(String),
+    DuplicateRotor(String),
+    /// A Greek wheel anywhere but the leftmost slot of a four rotor machine,
+    /// or a four rotor machine without one there.
+    MisplacedRotor(String),
+    /// Fewer or more rotors than the machine they belong to takes.
+    RotorCount {
+        given: usize,
+        expected: usize,
+    },
+    /// Rotors from more than one machine, reported at the first one that
+    /// doesn't match those before it.
+    MixedModels(String),
+    UnknownReflector(String),
+    UnknownEntry(String),
+    /// A UKW-D plug pair, or the whole set when there aren't twelve.
+    InvalidReflector(String),
+    ReflectorNotAllowed {
+        reflector: String,
+        model: Model,
+    },
+    /// A wheel wired to order that isn't a permutation of A-Z, or for a
+    /// reflector doesn't pair every letter off with another.
+    InvalidWiring(String),
+    /// A catalog file that can't be read, or reuses a name.
+    InvalidCatalog(String),
+    /// A saved machine that can't be read, or whose model doesn't match its
+    /// rotors.
+    InvalidConfig(String),
+    InvalidPosition(String),
+    InvalidRing(String),
+    /// A pair joining a letter to itself or to something not a letter.
+    InvalidPlugPair(char, char),
+    /// A letter given more than one cable.
+    DuplicatePlug(char),
+    /// Plug pairs that can't be read, or more cables than the thirteen there
+    /// are letters for.
+    InvalidPlugboard(String),
+    /// Uhr plug pairs that aren't ten pairs of distinct letters, or a
+    /// switch setting past 39.
+    InvalidUhr(String),
+    /// Plug pairs for a machine built without a plugboard.
+    NoPlugboard(Model),
+    /// A key sheet that can't be read, with the line (counted from 1) and
+    /// column it went wrong at.
+    InvalidKeySheet {
+        line: usize,
+        field: String,
+        reason: String,
+    },
+    /// A key sheet row with more or fewer fields than the header has
+    /// columns, with the line (counted from 1) it's on.
+    KeySheetRow {
+        line: usize,
+        given: usize,
+        expected: usize,
+    },
+    /// A received message too short for its indicator, a doubled message
+    /// key whose two copies don't agree, or a naval indicator trigram that
+    /// isn't three letters.
+    InvalidIndicator(String),
+    /// A bigram table swap that can't be read or reuses a bigram, or a
+    /// table that leaves some unpaired.
+    InvalidBigramTable(String),
+    /// A message key to encipher with no Grundstellung given or on the key
+    /// sheet.
+    NoGrundstellung,
+    /// A character with no key, at its byte offset in the text.
+    InvalidInput {
+        ch: char,
+        offset: usize,
+    },
+}
+
+impl fmt::Display for EnigmaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnigmaError::UnknownRotor(x) => {
+                write!(f, "{:?} Rotor doesn't exist, try an existing one", x)
+            }
+            EnigmaError::DuplicateRotor(x) => {
+                write!(f, "{:?} Rotor is used more than once", x)
+            }
+            EnigmaError::MisplacedRotor(x) => write!(
+                f,
+                "{:?} Rotor can't go there, Greek wheels only sit leftmost of four rotors",
+                x
+            ),
+            EnigmaError::RotorCount { given, expected } => {
+                write!(f, "{} rotors given, the machine takes {}", given, expected)
+            }
+            EnigmaError::MixedModels(x) => write!(
+                f,
+                "{:?} Rotor is from another machine, use rotors of a single model",
+                x
+            ),
+            EnigmaError::UnknownReflector(x) => {
+                write!(f, "{:?} Reflector doesn't exist, try an existing one", x)
+            }
+            EnigmaError::UnknownEntry(x) => {
+                write!(f, "{:?} Entry wheel doesn't exist, try an existing one", x)
+            }
+            EnigmaError::InvalidReflector(x) => write!(
+                f,
+                "{:?} Reflector wiring not valid, give twelve pairs of distinct letters",
+                x
+            ),
+            EnigmaError::ReflectorNotAllowed { reflector, model } => write!(
+                f,
+                "{:?} Reflector not valid, cant choose this reflector for the {:?} model",
+                reflector,
+                model.to_string()
+            ),
+            EnigmaError::InvalidWiring(x) => write!(
+                f,
+                "{:?} wiring not valid, give each letter A-Z once and pair them all off for a reflector",
+                x
+            ),
+            EnigmaError::InvalidCatalog(x) => write!(f, "catalog not valid, {}", x),
+            EnigmaError::InvalidConfig(x) => write!(f, "machine config not valid, {}", x),
+            EnigmaError::InvalidPosition(x) => write!(
+                f,
+                "{:?} Code settings not valid, give one letter A-Z per rotor",
+                x
+            ),
+            EnigmaError::InvalidRing(x) => write!(
+                f,
+                "{:?} Ring settings not valid, give one letter A-Z or number 01-26 per rotor",
+                x
+            ),
+            EnigmaError::InvalidPlugPair(a, b) => {
+                write!(
+                    f,
+                    "{:?} Plug pair not valid, give two different letters A-Z",
+                    (a, b)
+                )
+            }
+            EnigmaError::DuplicatePlug(x) => {
+                write!(f, "{:?} is plugged more than once, give each letter one cable", x)
+            }
+            EnigmaError::InvalidPlugboard(x) => write!(
+                f,
+                "{:?} Plugboard not valid, give at most 13 pairs like \"AV BS CG\"",
+                x
+            ),
+            EnigmaError::InvalidUhr(x) => write!(
+                f,
+                "{} Uhr settings not valid, give ten pairs of distinct letters and a setting 00-39",
+                x
+            ),
+            EnigmaError::NoPlugboard(x) => {
+                write!(
+                    f,
+                    "{:?} model has no plugboard, leave the plug pairs out",
+                    x.to_string()
+                )
+            }
+            EnigmaError::InvalidKeySheet {
+                line,
+                field,
+                reason,
+            } => write!(f, "key sheet line {}, {}: {}", line, field, reason),
+            EnigmaError::KeySheetRow {
+                line,
+                given,
+                expected,
+            } => write!(
+                f,
+                "key sheet line {}: {} fields given, the header has {} columns",
+                line, given, expected
+            ),
+            EnigmaError::InvalidIndicator(x) => write!(
+                f,
+                "{:?} Indicator not valid, give one or two message keys' worth of letters",
+                x
+            ),
+            EnigmaError::InvalidBigramTable(x) => write!(
+                f,
+                "{:?} Bigram table not valid, pair off every two letters once like \"AA=JX\"",
+                x
+            ),
+            EnigmaError::NoGrundstellung => write!(
+                f,
+                "no Grundstellung to encipher the message key at, give one"
+            ),
+            EnigmaError::InvalidInput { ch, offset } => {
+                write!(f, "{:?} at byte {} has no key on the keyboard", ch, offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EnigmaError {}
@@ -0,0 +1,509 @@
+use std::{collections::HashSet, fmt, str::FromStr};
+
+use crate::{
+    enigma::Enigma,
+    error::EnigmaError,
+    model::Model,
+    plugboard::Plugboard,
+    util::{settings, Random},
+};
+
+/// A monthly key sheet (Schlüsselblatt), one row of settings per day.
+///
+/// Sheets are plain text with columns split by `|`. The first row that
+/// isn't blank or a `#` comment names the columns, in any order:
+///
+/// - `Datum`: the day of the month, 1-31. Required.
+/// - `Walzenlage`: the rotor order, leftmost first, as "V I IV" or
+///   "Beta II IV I". Required.
+/// - `Ringstellung`: ring settings as numbers ("14 09 24") or letters.
+///   Required.
+/// - `Umkehrwalze`: the reflector, "B" or "C" (thin on a four rotor
+///   machine) or any reflector name. B when left out.
+/// - `Steckerverbindungen`: plug pairs, "SZ GT DV".
+/// - `Grundstellung`: the ground setting the message keys were enciphered
+///   at, before 1940.
+/// - `Kenngruppen`: the day's three letter identification groups.
+///
+/// ```text
+/// # Luftwaffe key sheet, May 1941
+/// Datum | Walzenlage | Ringstellung | Steckerverbindungen           | Kenngruppen
+/// 31    | V I IV     | 14 09 24     | SZ GT DV KU FO MY EW JN IX LQ | wny dgy ekb rzs
+/// 30    | IV III II  | 05 26 02     | IS EV MX RW DT UZ JQ AO CH NY | xjh cpq mqf pwt
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeySheet {
+    pub days: Vec<DailyKey>,
+}
+
+/// The settings for one day of a `KeySheet`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyKey {
+    pub day: u32,
+    pub reflector: String,
+    pub rotors: Vec<String>,
+    pub rings: String,
+    pub plugboard: Plugboard,
+    pub grundstellung: Option<String>,
+    pub kenngruppen: Vec<String>,
+}
+
+const DATUM: &str = "Datum";
+const UMKEHRWALZE: &str = "Umkehrwalze";
+const WALZENLAGE: &str = "Walzenlage";
+const RINGSTELLUNG: &str = "Ringstellung";
+const STECKERVERBINDUNGEN: &str = "Steckerverbindungen";
+const GRUNDSTELLUNG: &str = "Grundstellung";
+const KENNGRUPPEN: &str = "Kenngruppen";
+
+const COLUMNS: [&str; 7] = [
+    DATUM,
+    UMKEHRWALZE,
+    WALZENLAGE,
+    RINGSTELLUNG,
+    STECKERVERBINDUNGEN,
+    GRUNDSTELLUNG,
+    KENNGRUPPEN,
+];
+
+impl KeySheet {
+    /// The key for a day of the month.
+    pub fn day(&self, day: u32) -> Option<&DailyKey> {
+        self.days.iter().find(|key| key.day == day)
+    }
+
+    /// A month of random keys for an M3 or M4, days 1 to `days` (at most
+    /// 31) printed last day first, the way sheets were cut up as the month
+    /// went on. The same `seed` always gives the same sheet.
+    ///
+    /// Each day gets three of rotors I-V, or a Greek wheel and three of
+    /// I-VIII on the M4, reflector B or C, ten cables, a Grundstellung and
+    /// four Kenngruppen not used elsewhere on the sheet.
+    pub fn generate(model: Model, days: u32, seed: u64) -> Result<Self, EnigmaError> {
+        if !model.plugboard() {
+            return Err(EnigmaError::NoPlugboard(model));
+        }
+
+        let mut random = Random::new(seed);
+        let letters = |random: &mut Random, n| {
+            (0..n)
+                .map(|_| (random.below(26) as u8 + b'A') as char)
+                .collect::<String>()
+        };
+        let mut used = HashSet::new();
+        let mut sheet = KeySheet { days: vec![] };
+
+        for day in (1..=days.min(31)).rev() {
+            let (mut rotors, reflectors) = match model {
+                Model::M4 => (
+                    vec!["i", "ii", "iii", "iv", "v", "vi", "vii", "viii"],
+                    ["bthin", "cthin"],
+                ),
+                _ => (vec!["i", "ii", "iii", "iv", "v"], ["ukwb", "ukwc"]),
+            };
+            random.shuffle(&mut rotors);
+            rotors.truncate(3);
+            if model == Model::M4 {
+                rotors.insert(0, ["beta", "gamma"][random.below(2)]);
+            }
+
+            let rings = (0..rotors.len())
+                .map(|_| format!("{:02}", random.below(26) + 1))
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            let mut plugs = ('A'..='Z').collect::<Vec<_>>();
+            random.shuffle(&mut plugs);
+            let pairs = plugs[..20]
+                .chunks(2)
+                .map(|pair| (pair[0], pair[1]))
+                .collect::<Vec<_>>();
+
+            let grundstellung = letters(&mut random, rotors.len());
+
+            let mut kenngruppen = vec![];
+            while kenngruppen.len() < 4 {
+                let group = letters(&mut random, 3);
+                if used.insert(group.clone()) {
+                    kenngruppen.push(group);
+                }
+            }
+
+            sheet.days.push(DailyKey {
+                day,
+                reflector: reflectors[random.below(2)].to_string(),
+                rotors: rotors.into_iter().map(str::to_string).collect(),
+                rings,
+                plugboard: Plugboard::from_pairs(&pairs)?,
+                grundstellung: Some(grundstellung),
+                kenngruppen,
+            });
+        }
+
+        Ok(sheet)
+    }
+}
+
+impl DailyKey {
+    /// A machine set up with the day's key, its rotors turned to `positions`
+    /// or, without them, to the Grundstellung if the sheet has one.
+    pub fn enigma(&self, positions: Option<&str>) -> Result<Enigma, EnigmaError> {
+        let rotors = self.rotors.iter().map(String::as_str).collect::<Vec<_>>();
+
+        Enigma::from_config(
+            &rotors,
+            &self.reflector,
+            positions.or(self.grundstellung.as_deref()),
+            Some(&self.rings),
+            Some(&self.plugboard.pairs()),
+        )
+    }
+
+    fn parse<'a>(
+        number: usize,
+        row: impl Fn(&str) -> Option<&'a str>,
+    ) -> Result<Self, EnigmaError> {
+        let field = |column| row(column).unwrap_or_default();
+        let fail =
+            |column: &'static str| move |e: EnigmaError| invalid(number, column, &e.to_string());
+
+        let day = match field(DATUM).parse() {
+            Ok(day) if (1..=31).contains(&day) => day,
+            _ => return Err(invalid(number, DATUM, "give a day of the month 1-31")),
+        };
+
+        let rotors = field(WALZENLAGE)
+            .split([' ', ','])
+            .filter(|r| !r.is_empty())
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+
+        // sheets name the reflector by letter, thin on a four rotor machine
+        let reflector = match (field(UMKEHRWALZE).to_lowercase().as_str(), rotors.len()) {
+            ("" | "b", 4) => "bthin".to_string(),
+            ("c", 4) => "cthin".to_string(),
+            ("" | "b", _) => "ukwb".to_string(),
+            ("c", _) => "ukwc".to_string(),
+            (x, _) => x.to_string(),
+        };
+
+        let rings = field(RINGSTELLUNG).to_string();
+        if settings(&rings).is_none() {
+            return Err(invalid(
+                number,
+                RINGSTELLUNG,
+                "give one letter or number per rotor",
+            ));
+        }
+
+        let plugboard =
+            Plugboard::from_str(field(STECKERVERBINDUNGEN)).map_err(fail(STECKERVERBINDUNGEN))?;
+
+        let grundstellung = row(GRUNDSTELLUNG)
+            .filter(|g| !g.is_empty())
+            .map(str::to_uppercase);
+
+        let kenngruppen = field(KENNGRUPPEN)
+            .split_whitespace()
+            .map(
+                |group| match group.len() == 3 && group.chars().all(|c| c.is_ascii_alphabetic()) {
+                    true => Ok(group.to_uppercase()),
+                    false => Err(invalid(number, KENNGRUPPEN, "give groups of three letters")),
+                },
+            )
+            .collect::<Result<_, _>>()?;
+
+        let key = DailyKey {
+            day,
+            reflector,
+            rotors,
+            rings,
+            plugboard,
+            grundstellung,
+            kenngruppen,
+        };
+
+        // whatever the fields can't say on their own comes out building the
+        // machine, put down to the column it came from
+        key.enigma(None).map_err(|e| {
+            let column = match e {
+                EnigmaError::UnknownReflector(_)
+                | EnigmaError::InvalidReflector(_)
+                | EnigmaError::ReflectorNotAllowed { .. } => UMKEHRWALZE,
+                EnigmaError::InvalidRing(_) => RINGSTELLUNG,
+                EnigmaError::InvalidPosition(_) => GRUNDSTELLUNG,
+                EnigmaError::NoPlugboard(_) => STECKERVERBINDUNGEN,
+                _ => WALZENLAGE,
+            };
+            fail(column)(e)
+        })?;
+
+        Ok(key)
+    }
+}
+
+impl FromStr for KeySheet {
+    type Err = EnigmaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        let (number, header) = lines
+            .next()
+            .ok_or_else(|| invalid(1, DATUM, "no header row"))?;
+        let columns = header
+            .split('|')
+            .map(|name| {
+                COLUMNS
+                    .into_iter()
+                    .find(|c| c.eq_ignore_ascii_case(name.trim()))
+                    .ok_or_else(|| invalid(number, name.trim(), "not a key sheet column"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for required in [DATUM, WALZENLAGE, RINGSTELLUNG] {
+            if !columns.contains(&required) {
+                return Err(invalid(number, required, "column missing from the header"));
+            }
+        }
+
+        let mut sheet = KeySheet { days: vec![] };
+        for (number, line) in lines {
+            let fields = line.split('|').map(str::trim).collect::<Vec<_>>();
+            if fields.len() != columns.len() {
+                return Err(EnigmaError::KeySheetRow {
+                    line: number,
+                    given: fields.len(),
+                    expected: columns.len(),
+                });
+            }
+
+            let row = |column: &str| columns.iter().position(|c| *c == column).map(|i| fields[i]);
+
+            let key = DailyKey::parse(number, row)?;
+            if sheet.day(key.day).is_some() {
+                return Err(invalid(number, DATUM, "day is already on the sheet"));
+            }
+
+            sheet.days.push(key);
+        }
+
+        Ok(sheet)
+    }
+}
+
+/// Prints the sheet in the format it's read in, every column present and
+/// lined up.
+impl fmt::Display for KeySheet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rows = vec![COLUMNS.map(str::to_string).to_vec()];
+        for key in &self.days {
+            let reflector = match key.reflector.as_str() {
+                "ukwb" | "bthin" => "B".to_string(),
+                "ukwc" | "cthin" => "C".to_string(),
+                x => x.to_string(),
+            };
+            let rotors = key
+                .rotors
+                .iter()
+                .map(|r| match r.as_str() {
+                    "beta" => "Beta".to_string(),
+                    "gamma" => "Gamma".to_string(),
+                    r => r.to_uppercase(),
+                })
+                .collect::<Vec<_>>();
+            let kenngruppen = key
+                .kenngruppen
+                .iter()
+                .map(|group| group.to_lowercase())
+                .collect::<Vec<_>>();
+
+            // in the order of COLUMNS
+            rows.push(vec![
+                key.day.to_string(),
+                reflector,
+                rotors.join(" "),
+                key.rings.clone(),
+                key.plugboard.to_string(),
+                key.grundstellung.clone().unwrap_or_default(),
+                kenngruppen.join(" "),
+            ]);
+        }
+
+        let widths = (0..COLUMNS.len())
+            .map(|i| {
+                rows.iter()
+                    .map(|row| row[i].chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+
+        let lines = rows.iter().map(|row| {
+            let fields = row
+                .iter()
+                .zip(&widths)
+                .map(|(field, width)| format!("{:width$}", field, width = width))
+                .collect::<Vec<_>>();
+            fields.join(" | ").trim_end().to_string()
+        });
+
+        write!(f, "{}", lines.collect::<Vec<_>>().join("\n"))
+    }
+}
+
+fn invalid(line: usize, field: &str, reason: &str) -> EnigmaError {
+    EnigmaError::InvalidKeySheet {
+        line,
+        field: field.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[test]
+pub fn parse_key_sheet() {
+    let sheet = KeySheet::from_str(
+        "
+        # Luftwaffe key sheet, May 1941
+        Datum | Walzenlage | Ringstellung | Steckerverbindungen           | Kenngruppen
+        31    | V I IV     | 14 09 24     | SZ GT DV KU FO MY EW JN IX LQ | wny dgy ekb rzs
+        30    | IV III II  | 05 26 02     | IS EV MX RW DT UZ JQ AO CH NY | xjh cpq mqf pwt
+        ",
+    )
+    .unwrap();
+
+    let key = sheet.day(31).unwrap();
+    assert_eq!(key.rotors, ["v", "i", "iv"]);
+    assert_eq!(key.reflector, "ukwb");
+    assert_eq!(key.plugboard.to_string(), "DV EW FO GT IX JN KU LQ MY SZ");
+    assert_eq!(key.kenngruppen, ["WNY", "DGY", "EKB", "RZS"]);
+    assert_eq!(key.grundstellung, None);
+    assert!(sheet.day(29).is_none());
+
+    let plugs = key.plugboard.pairs();
+    assert_eq!(
+        key.enigma(Some("ABC")).unwrap().encode("KEYSHEET"),
+        Enigma::from_config(
+            &["v", "i", "iv"],
+            "ukwb",
+            Some("ABC"),
+            Some("14 09 24"),
+            Some(&plugs)
+        )
+        .unwrap()
+        .encode("KEYSHEET")
+    );
+
+    let sheet = KeySheet::from_str(
+        "Umkehrwalze | Datum | Walzenlage    | Ringstellung | Grundstellung\n\
+         C           | 1     | Beta II IV I  | A A A V      | vjna",
+    )
+    .unwrap();
+    let key = sheet.day(1).unwrap();
+    assert_eq!(key.reflector, "cthin");
+    assert_eq!(key.enigma(None).unwrap().positions(), "VJNA");
+}
+
+#[test]
+pub fn parse_bad_key_sheet() {
+    let header = "Datum | Walzenlage | Ringstellung | Steckerverbindungen\n";
+    let error = |row: &str| match KeySheet::from_str(&(header.to_string() + row)) {
+        Err(EnigmaError::InvalidKeySheet { line, field, .. }) => (line, field),
+        x => panic!("{:?}", x),
+    };
+
+    assert_eq!(
+        error("32 | I II III | 01 01 01 | AB"),
+        (2, DATUM.to_string())
+    );
+    assert_eq!(
+        error("1 | I II | 01 01 01 | AB"),
+        (2, WALZENLAGE.to_string())
+    );
+    assert_eq!(
+        error("1 | I II IX | 01 01 01 | AB"),
+        (2, WALZENLAGE.to_string())
+    );
+    assert_eq!(
+        error("1 | I II III | 01 01 | AB"),
+        (2, RINGSTELLUNG.to_string())
+    );
+    assert_eq!(
+        error("1 | I II III | 01 01 99 | AB"),
+        (2, RINGSTELLUNG.to_string())
+    );
+    assert_eq!(
+        error("1 | I II III | 01 01 01 | AB AC"),
+        (2, STECKERVERBINDUNGEN.to_string())
+    );
+    let row = KeySheet::from_str(&(header.to_string() + "1 | I II III | 01 01 01"));
+    assert_eq!(
+        row,
+        Err(EnigmaError::KeySheetRow {
+            line: 2,
+            given: 3,
+            expected: 4
+        })
+    );
+    assert_eq!(
+        row.unwrap_err().to_string(),
+        "key sheet line 2: 3 fields given, the header has 4 columns"
+    );
+    assert_eq!(
+        error("1 | I II III | 01 01 01 | AB\n\n# again\n1 | I II III | 01 01 01 | AB"),
+        (5, DATUM.to_string())
+    );
+
+    assert_eq!(
+        KeySheet::from_str("Datum | Walzen | Ringstellung"),
+        Err(invalid(1, "Walzen", "not a key sheet column"))
+    );
+    assert_eq!(
+        KeySheet::from_str("Datum | Ringstellung")
+            .unwrap_err()
+            .to_string(),
+        "key sheet line 1, Walzenlage: column missing from the header"
+    );
+}
+
+#[test]
+pub fn generate_key_sheet() {
+    let sheet = KeySheet::generate(Model::M4, 30, 1941).unwrap();
+    assert_eq!(sheet, KeySheet::generate(Model::M4, 30, 1941).unwrap());
+    assert_ne!(sheet, KeySheet::generate(Model::M4, 30, 1942).unwrap());
+    assert_eq!(sheet.days.len(), 30);
+    assert_eq!(sheet.days[0].day, 30);
+    assert_eq!(KeySheet::from_str(&sheet.to_string()), Ok(sheet.clone()));
+
+    let mut kenngruppen = HashSet::new();
+    for key in &sheet.days {
+        assert!(["beta", "gamma"].contains(&key.rotors[0].as_str()));
+        assert!(key.rotors[1..]
+            .iter()
+            .all(|r| !["beta", "gamma"].contains(&r.as_str())));
+        assert_eq!(key.rotors.iter().collect::<HashSet<_>>().len(), 4);
+        assert_eq!(key.plugboard.pairs().len(), 10);
+        assert!(key
+            .kenngruppen
+            .iter()
+            .all(|group| kenngruppen.insert(group)));
+        assert!(key.enigma(None).is_ok());
+    }
+
+    let sheet = KeySheet::generate(Model::M3, 40, 7).unwrap();
+    assert_eq!(sheet.days.len(), 31);
+    assert!(sheet.days.iter().all(|key| key.rotors.len() == 3));
+    assert_eq!(
+        sheet.to_string().lines().next(),
+        Some("Datum | Umkehrwalze | Walzenlage | Ringstellung | Steckerverbindungen           | Grundstellung | Kenngruppen")
+    );
+
+    assert_eq!(
+        KeySheet::generate(Model::T, 31, 7),
+        Err(EnigmaError::NoPlugboard(Model::T))
+    );
+}
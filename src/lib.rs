@@ -0,0 +1,34 @@
+//! A simulator of the Enigma M3/M4 cipher machines, the commercial models
+//! D, K, Swiss-K and Railway, the Abwehr's Enigma G and the Enigma T.
+//!
+//! ```
+//! use enigma::Enigma;
+//!
+//! let mut enigma = Enigma::from_config(&["i", "ii", "iii"], "ukwb", None, None, None)?;
+//! assert_eq!(enigma.encode("AAAAA"), "BDZGO");
+//! # Ok::<(), enigma::EnigmaError>(())
+//! ```
+
+mod catalog;
+mod circuit;
+mod enigma;
+mod error;
+mod keysheet;
+mod model;
+mod naval;
+mod plugboard;
+mod procedure;
+mod rotors;
+mod uhr;
+mod util;
+
+pub use crate::catalog::Catalog;
+pub use crate::enigma::{Enigma, Input, State};
+pub use crate::error::EnigmaError;
+pub use crate::keysheet::{DailyKey, KeySheet};
+pub use crate::model::Model;
+pub use crate::naval::{BigramTable, Indicator};
+pub use crate::plugboard::Plugboard;
+pub use crate::procedure::Procedure;
+pub use crate::rotors::{Custom, Entry, Notation, Reflector, Rotor, Rotors, Wheel};
+pub use crate::uhr::Uhr;
@@ -1,13 +1,350 @@
-use crate::enigma::Enigma;
+use std::{
+    env, fmt, fs,
+    io::{self, Read, Write},
+    process::ExitCode,
+};
 
-mod rotors;
-mod circuit;
-mod enigma;
-mod util;
+use enigma::{Catalog, Enigma, EnigmaError, Input, KeySheet, Plugboard, Uhr};
 
-fn main() {
-    let plugboard = [('X', 'S'), ('F', 'V')];
-    let mut enigma = Enigma::from_config(&["i", "ii", "iii"], "ukwc", None, Some(&plugboard));
+const USAGE: &str = "\
+Usage: enigma [OPTIONS] [TEXT]...
 
-    println!("{}", enigma.encode("EPAUK PEDJERBYLE HIIXJ MQVLJDNCYF BYLUZ QMVGOVQXXC JFGOF CWJEEMPWUZ GFWLF ZMUBHZRWXQ GDAWH KCIAUIJSWO CGSRH NRFKQPDHLT QYLBE FRGMLQEJMB NOUYD JBYITCVNMM KGGIP VYMVGSCFHN"))
+Enciphers TEXT, or the --input file, or standard input when neither is given.
+
+Options:
+  -r, --rotors <LIST>        rotor order, leftmost first [default: i,ii,iii]
+  -u, --reflector <NAME>     reflector (Umkehrwalze) [default: ukwb]
+  -p, --positions <LETTERS>  start positions, e.g. ABC or VJNA for an M4, led by
+                             the reflector's on commercial machines
+  -g, --rings <SETTINGS>     ring settings as letters (BUL) or numbers (02,21,12)
+  -s, --plugboard <PAIRS>    plug pairs, e.g. \"AV BS CG\" or AV-BS-CG
+  -U, --uhr <SETTING>        plug the ten --plugboard pairs into an Uhr switched to 00-39
+  -e, --entry <NAME>         entry wheel: identity, qwertzu, tirpitz or one from the catalog
+                             [default: the model's own]
+  -c, --catalog <FILE>       also pick rotors, reflectors and entry wheels from a TOML or
+                             JSON catalog
+  -K, --key-sheet <FILE>     take the rotors, reflector, rings and plugs from a key sheet,
+                             in place of -r, -u, -g and -s
+  -d, --day <DAY>            the key sheet's day of the month to use
+  -k, --keys <POLICY>        characters with no key: reject, drop, pass or a letter [default: pass]
+  -i, --input <FILE>         read the text from FILE
+  -o, --output <FILE>        write the result to FILE instead of standard output
+  -h, --help                 print this help";
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    rotors: Vec<String>,
+    reflector: String,
+    positions: Option<String>,
+    rings: Option<String>,
+    plugboard: String,
+    uhr: Option<usize>,
+    entry: Option<String>,
+    catalog: Option<String>,
+    key_sheet: Option<String>,
+    day: Option<u32>,
+    keys: Input,
+    input: Option<String>,
+    output: Option<String>,
+    text: Vec<String>,
+    help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            rotors: vec!["i".to_string(), "ii".to_string(), "iii".to_string()],
+            reflector: "ukwb".to_string(),
+            positions: None,
+            rings: None,
+            plugboard: String::new(),
+            uhr: None,
+            entry: None,
+            catalog: None,
+            key_sheet: None,
+            day: None,
+            keys: Input::PassThrough,
+            input: None,
+            output: None,
+            text: vec![],
+            help: false,
+        }
+    }
+}
+
+enum Failure {
+    Usage(String),
+    Config(EnigmaError),
+    Io(String, io::Error),
+}
+
+impl Failure {
+    fn code(&self) -> u8 {
+        match self {
+            Failure::Usage(_) | Failure::Config(_) => 2,
+            Failure::Io(..) => 1,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Usage(x) => write!(f, "{}\n\n{}", x, USAGE),
+            Failure::Config(x) => write!(f, "{}", x),
+            Failure::Io(x, e) => write!(f, "{}: {}", x, e),
+        }
+    }
+}
+
+fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, Failure> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    // the options a key sheet sets, as given
+    let mut keyed = vec![];
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| Failure::Usage(format!("{:?} needs a value", arg)))
+        };
+
+        if let "-r" | "--rotors" | "-u" | "--reflector" | "-g" | "--rings" | "-s" | "--plugboard" =
+            arg.as_str()
+        {
+            keyed.push(arg.clone());
+        }
+
+        match arg.as_str() {
+            "-r" | "--rotors" => {
+                options.rotors = value()?
+                    .split([',', ' '])
+                    .filter(|r| !r.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }
+            "-u" | "--reflector" => options.reflector = value()?,
+            "-p" | "--positions" => options.positions = Some(value()?),
+            "-g" | "--rings" => options.rings = Some(value()?),
+            "-s" | "--plugboard" => {
+                let pairs = value()?;
+                pairs
+                    .parse::<Plugboard>()
+                    .map_err(|_| Failure::Usage(format!("{:?} aren't plug pairs", pairs)))?;
+                options.plugboard = pairs
+            }
+            "-U" | "--uhr" => {
+                let setting = value()?;
+                options.uhr =
+                    Some(setting.parse().map_err(|_| {
+                        Failure::Usage(format!("{:?} isn't an Uhr setting", setting))
+                    })?)
+            }
+            "-e" | "--entry" => options.entry = Some(value()?),
+            "-c" | "--catalog" => options.catalog = Some(value()?),
+            "-K" | "--key-sheet" => options.key_sheet = Some(value()?),
+            "-d" | "--day" => {
+                let day = value()?;
+                options.day = Some(
+                    day.parse()
+                        .map_err(|_| Failure::Usage(format!("{:?} isn't a day", day)))?,
+                )
+            }
+            "-k" | "--keys" => options.keys = keys(&value()?)?,
+            "-i" | "--input" => options.input = Some(value()?),
+            "-o" | "--output" => options.output = Some(value()?),
+            "-h" | "--help" => options.help = true,
+            "--" => options.text.extend(args.by_ref()),
+            x if x.starts_with('-') && x.len() > 1 => {
+                return Err(Failure::Usage(format!("{:?} isn't an option", x)))
+            }
+            _ => options.text.push(arg),
+        }
+    }
+
+    if options.input.is_some() && !options.text.is_empty() {
+        return Err(Failure::Usage(
+            "give the text either as arguments or with --input, not both".to_string(),
+        ));
+    }
+
+    if options.key_sheet.is_some() != options.day.is_some() {
+        return Err(Failure::Usage(
+            "give --key-sheet and --day together".to_string(),
+        ));
+    }
+
+    if let (Some(_), Some(option)) = (&options.key_sheet, keyed.first()) {
+        return Err(Failure::Usage(format!(
+            "{:?} can't be given with --key-sheet, which sets it",
+            option
+        )));
+    }
+
+    Ok(options)
+}
+
+fn keys(s: &str) -> Result<Input, Failure> {
+    let mut letter = s.chars();
+    match (s, letter.next(), letter.next()) {
+        ("reject", ..) => Ok(Input::Reject),
+        ("drop", ..) => Ok(Input::Drop),
+        ("pass", ..) => Ok(Input::PassThrough),
+        (_, Some(c), None) if c.is_ascii_alphabetic() => Ok(Input::Substitute(c)),
+        _ => Err(Failure::Usage(format!("{:?} isn't a key policy", s))),
+    }
+}
+
+fn run(mut options: Options) -> Result<(), Failure> {
+    if let (Some(path), Some(day)) = (&options.key_sheet, options.day) {
+        let sheet = fs::read_to_string(path).map_err(|e| Failure::Io(path.to_string(), e))?;
+        let sheet = sheet.parse::<KeySheet>().map_err(Failure::Config)?;
+        let key = sheet
+            .day(day)
+            .ok_or_else(|| Failure::Usage(format!("{:?} has no key for day {}", path, day)))?;
+
+        options.rotors = key.rotors.clone();
+        options.reflector = key.reflector.clone();
+        options.rings = Some(key.rings.clone());
+        options.plugboard = key.plugboard.to_string();
+        options.positions = options.positions.or(key.grundstellung.clone());
+    }
+
+    let rotors = options
+        .rotors
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let catalog = match &options.catalog {
+        Some(path) => Catalog::load(path).map_err(Failure::Config)?,
+        None => Catalog::new(),
+    };
+    let mut wheels = catalog
+        .rotors(&rotors, &options.reflector)
+        .map_err(Failure::Config)?;
+    if let Some(entry) = &options.entry {
+        wheels.entry = Some(catalog.entry(entry).map_err(Failure::Config)?);
+    }
+
+    // with an Uhr in, the cables go into it rather than the plugboard
+    let (plugboard, uhr) = match options.uhr {
+        Some(setting) => {
+            let mut uhr = options.plugboard.parse::<Uhr>().map_err(Failure::Config)?;
+            uhr.set(setting).map_err(Failure::Config)?;
+            (Plugboard::new(), Some(uhr))
+        }
+        None => (options.plugboard.parse().map_err(Failure::Config)?, None),
+    };
+
+    let mut enigma = Enigma::from_rotors(
+        wheels,
+        options.positions.as_deref(),
+        options.rings.as_deref(),
+        None,
+    )
+    .map_err(Failure::Config)?;
+    enigma.set_plugboard(plugboard).map_err(Failure::Config)?;
+    enigma.set_uhr(uhr).map_err(Failure::Config)?;
+    enigma.input = options.keys;
+
+    let text = match (&options.input, options.text.is_empty()) {
+        (Some(path), _) => {
+            fs::read_to_string(path).map_err(|e| Failure::Io(path.to_string(), e))?
+        }
+        (None, true) => {
+            let mut text = String::new();
+            io::stdin()
+                .read_to_string(&mut text)
+                .map_err(|e| Failure::Io("stdin".to_string(), e))?;
+            text
+        }
+        (None, false) => options.text.join(" ") + "\n",
+    };
+
+    let ciphertext = enigma.try_encode(&text).map_err(Failure::Config)?;
+
+    match &options.output {
+        Some(path) => fs::write(path, ciphertext).map_err(|e| Failure::Io(path.to_string(), e)),
+        None => io::stdout()
+            .write_all(ciphertext.as_bytes())
+            .map_err(|e| Failure::Io("stdout".to_string(), e)),
+    }
+}
+
+fn main() -> ExitCode {
+    let result = parse(env::args().skip(1)).and_then(|options| match options.help {
+        true => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        false => run(options),
+    });
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("enigma: {}", e);
+            ExitCode::from(e.code())
+        }
+    }
+}
+
+#[cfg(test)]
+fn args(s: &str) -> Vec<String> {
+    s.split_whitespace().map(str::to_string).collect()
+}
+
+#[test]
+pub fn parse_options() {
+    let options = parse(args(
+        "-r beta,v,vi,viii -u cthin -p VJNA --rings 01,01,05,12 -s AE-BF -e qwertzu -c wheels.toml -k drop HELLO WORLD",
+    ))
+    .ok()
+    .unwrap();
+
+    assert_eq!(
+        options,
+        Options {
+            rotors: args("beta v vi viii"),
+            reflector: "cthin".to_string(),
+            positions: Some("VJNA".to_string()),
+            rings: Some("01,01,05,12".to_string()),
+            plugboard: "AE-BF".to_string(),
+            entry: Some("qwertzu".to_string()),
+            catalog: Some("wheels.toml".to_string()),
+            keys: Input::Drop,
+            text: args("HELLO WORLD"),
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+pub fn parse_bad_options() {
+    for bad in [
+        "--rotor i",
+        "-r",
+        "-s ABC",
+        "-k 7",
+        "-U x",
+        "-K sheet.txt",
+        "-d 3x",
+        "-i file.txt HELLO",
+        "-K sheet.txt -d 3 -r i,ii,iii",
+        "-u ukwc -K sheet.txt -d 3",
+        "-K sheet.txt -d 3 --rings AAA",
+        "-K sheet.txt -d 3 -s AB",
+    ] {
+        assert!(
+            matches!(parse(args(bad)), Err(Failure::Usage(_))),
+            "{:?}",
+            bad
+        );
+    }
+
+    // a key sheet still leaves the start positions and the Uhr to choose
+    assert!(parse(args("-K sheet.txt -d 3 -p ABC -U 27")).is_ok());
+
+    let options = parse(args("-r i,ii,ix")).ok().unwrap();
+    assert!(matches!(run(options), Err(Failure::Config(_))));
 }
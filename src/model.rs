@@ -0,0 +1,190 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+
+use crate::{
+    circuit::Drive,
+    error::EnigmaError,
+    rotors::{Entry, Reflector, Wheel},
+};
+
+/// The machine a set of wheels belongs to. Each family has its own rotors,
+/// so the model is worked out from the rotor names rather than given.
+#[derive(Debug, Clone, Copy, Display, EnumString, PartialEq, Eq, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(into = "String", try_from = "String")]
+pub enum Model {
+    /// The Wehrmacht and Luftwaffe Enigma I, and the Kriegsmarine M3.
+    #[strum(ascii_case_insensitive)]
+    M3,
+    /// The Kriegsmarine's four rotor machine, with a Greek wheel leftmost.
+    #[strum(ascii_case_insensitive)]
+    M4,
+    /// The commercial Enigma D of 1926.
+    #[strum(ascii_case_insensitive)]
+    D,
+    /// The commercial Enigma K, wired as the D.
+    #[strum(ascii_case_insensitive)]
+    K,
+    /// The Enigma K as rewired for the Swiss army.
+    #[strum(to_string = "swiss-k", ascii_case_insensitive)]
+    SwissK,
+    /// The Reichsbahn's rewired Enigma K, the "Rocket" at Bletchley.
+    #[strum(ascii_case_insensitive)]
+    Railway,
+    /// The Abwehr's Enigma G, serial G-312, with a gear driven odometer.
+    #[strum(to_string = "g-312", ascii_case_insensitive)]
+    G312,
+    /// The Enigma G serial G-260, sent to Argentina.
+    #[strum(to_string = "g-260", ascii_case_insensitive)]
+    G260,
+    /// The Enigma T (Tirpitz) built for the Japanese navy, with five notches
+    /// on each of its eight rotors.
+    #[strum(ascii_case_insensitive)]
+    T,
+}
+
+impl Model {
+    /// Works out the model from the rotors, leftmost first. Military rotors
+    /// make an M4 when there are four of them and an M3 otherwise.
+    pub(crate) fn of(rotors: &[Wheel]) -> Result<Model, EnigmaError> {
+        let mut model = None;
+
+        for wheel in rotors {
+            let r = match wheel {
+                Wheel::Rotor(r) => r,
+                Wheel::Reflector(r) => return Err(EnigmaError::MisplacedRotor(r.to_string())),
+            };
+
+            // custom rotors fit in with any others
+            let family = match r.model() {
+                Some(Model::M4) => Model::M3,
+                Some(m) => m,
+                None => continue,
+            };
+
+            match model {
+                Some(m) if m != family => return Err(EnigmaError::MixedModels(r.to_string())),
+                _ => model = Some(family),
+            }
+        }
+
+        match model.unwrap_or(Model::M3) {
+            _ if rotors.is_empty() => Err(EnigmaError::RotorCount {
+                given: 0,
+                expected: Model::M3.rotors(),
+            }),
+            Model::M3 if rotors.len() == 4 => Ok(Model::M4),
+            m => Ok(m),
+        }
+    }
+
+    /// How many rotors the machine takes, a Greek wheel included.
+    pub fn rotors(&self) -> usize {
+        match self {
+            Model::M4 => 4,
+            _ => 3,
+        }
+    }
+
+    /// The entry wheel the machine was built with. The military machines
+    /// wire key A to contact A, the commercial ones kept the keyboard order
+    /// and the T scrambled it.
+    pub fn entry(&self) -> Entry {
+        match self {
+            Model::M3 | Model::M4 => Entry::Identity,
+            Model::T => Entry::Tirpitz,
+            _ => Entry::QWERTZU,
+        }
+    }
+
+    pub fn plugboard(&self) -> bool {
+        matches!(self, Model::M3 | Model::M4)
+    }
+
+    /// How the wheels move on each keypress. Only the Enigma G steps its
+    /// reflector, carried from the leftmost rotor.
+    pub(crate) fn drive(&self) -> Drive {
+        match self {
+            Model::G312 | Model::G260 => Drive::Gear,
+            _ => Drive::Lever,
+        }
+    }
+
+    /// Whether the reflector can be turned to a start position like a rotor.
+    pub fn settable_reflector(&self) -> bool {
+        !self.plugboard()
+    }
+
+    /// Whether the machine takes `reflector`. A custom one goes in any.
+    pub fn reflects(&self, reflector: &Reflector) -> bool {
+        if let Reflector::Custom(_) = reflector {
+            return true;
+        }
+
+        match self {
+            Model::M3 => matches!(
+                reflector,
+                Reflector::UKWB | Reflector::UKWC | Reflector::UKWD(_)
+            ),
+            Model::M4 => matches!(reflector, Reflector::BTHIN | Reflector::CTHIN),
+            Model::D => matches!(reflector, Reflector::DUKW),
+            Model::K => matches!(reflector, Reflector::KUKW),
+            Model::SwissK => matches!(reflector, Reflector::SWISSKUKW),
+            Model::Railway => matches!(reflector, Reflector::RAILWAYUKW),
+            Model::G312 => matches!(reflector, Reflector::G312UKW),
+            Model::G260 => matches!(reflector, Reflector::G260UKW),
+            Model::T => matches!(reflector, Reflector::TUKW),
+        }
+    }
+}
+
+impl From<Model> for String {
+    fn from(x: Model) -> Self {
+        x.to_string()
+    }
+}
+
+impl TryFrom<String> for Model {
+    type Error = String;
+
+    fn try_from(x: String) -> Result<Self, Self::Error> {
+        Model::from_str(&x).map_err(|_| format!("{:?} isn't a model", x))
+    }
+}
+
+#[test]
+pub fn infer_model() {
+    use crate::rotors::Rotor;
+
+    let wheels = |names: &[Rotor]| names.iter().cloned().map(Wheel::Rotor).collect::<Vec<_>>();
+
+    assert_eq!(
+        Model::of(&wheels(&[Rotor::I, Rotor::II, Rotor::III])),
+        Ok(Model::M3)
+    );
+    assert_eq!(
+        Model::of(&wheels(&[Rotor::BETA, Rotor::I, Rotor::II, Rotor::III])),
+        Ok(Model::M4)
+    );
+    assert_eq!(
+        Model::of(&wheels(&[
+            Rotor::SWISSKIII,
+            Rotor::SWISSKI,
+            Rotor::SWISSKII
+        ])),
+        Ok(Model::SwissK)
+    );
+    assert_eq!(
+        Model::of(&wheels(&[Rotor::DI, Rotor::KII, Rotor::DIII])),
+        Err(EnigmaError::MixedModels("k-ii".to_string()))
+    );
+    assert_eq!(
+        Model::of(&[]),
+        Err(EnigmaError::RotorCount {
+            given: 0,
+            expected: Model::M3.rotors(),
+        })
+    );
+}
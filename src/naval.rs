@@ -0,0 +1,369 @@
+use std::{fmt, fs, path::Path, str::FromStr};
+
+use crate::{error::EnigmaError, keysheet::DailyKey, util::settings};
+
+/// A bigram table (Doppelbuchstabentauschtafel), swapping every pair of
+/// letters for another. Tables are reciprocal: if AA becomes JX, JX becomes
+/// AA. Written as the 338 swaps, `AA=JX`, split by spaces, commas or lines,
+/// with `#` starting a comment line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BigramTable {
+    swaps: Vec<u16>,
+}
+
+impl BigramTable {
+    /// Reads a table from a file in the format above.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, EnigmaError> {
+        let path = path.as_ref();
+        fs::read_to_string(path)
+            .map_err(|e| EnigmaError::InvalidBigramTable(format!("{}: {}", path.display(), e)))?
+            .parse()
+    }
+
+    /// The pair of letters `a` and `b` are swapped for.
+    pub fn swap(&self, a: char, b: char) -> Result<(char, char), EnigmaError> {
+        let bigram = bigram(&format!("{}{}", a, b))?;
+        let out = self.swaps[bigram as usize];
+
+        Ok((letter(out / 26), letter(out % 26)))
+    }
+}
+
+impl FromStr for BigramTable {
+    type Err = EnigmaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut swaps = vec![u16::MAX; 676];
+
+        let tokens = s
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .flat_map(|line| line.split([' ', ',', '\t']))
+            .filter(|token| !token.is_empty());
+
+        for token in tokens {
+            let invalid = || EnigmaError::InvalidBigramTable(token.to_string());
+            let (x, y) = token.split_once('=').ok_or_else(invalid)?;
+            let (x, y) = (bigram(x)?, bigram(y)?);
+
+            if x == y || swaps[x as usize] != u16::MAX || swaps[y as usize] != u16::MAX {
+                return Err(invalid());
+            }
+
+            swaps[x as usize] = y;
+            swaps[y as usize] = x;
+        }
+
+        let missing = swaps.iter().filter(|x| **x == u16::MAX).count();
+        if missing > 0 {
+            return Err(EnigmaError::InvalidBigramTable(format!(
+                "{} bigrams unpaired",
+                missing
+            )));
+        }
+
+        Ok(BigramTable { swaps })
+    }
+}
+
+/// Thirteen swaps to a line, in order of their first bigram.
+impl fmt::Display for BigramTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let swaps = self
+            .swaps
+            .iter()
+            .enumerate()
+            .filter(|(x, y)| *x < **y as usize)
+            .map(|(x, y)| {
+                let x = x as u16;
+                format!(
+                    "{}{}={}{}",
+                    letter(x / 26),
+                    letter(x % 26),
+                    letter(y / 26),
+                    letter(y % 26)
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let lines = swaps.chunks(13).map(|line| line.join(" "));
+        write!(f, "{}", lines.collect::<Vec<_>>().join("\n"))
+    }
+}
+
+/// The two trigrams a naval operator picks out of the Kenngruppenbuch for a
+/// message: the Schlüsselkenngruppe, telling which key the message is in,
+/// and the Verfahrenkenngruppe, which enciphered at the day's Grundstellung
+/// gives the message key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Indicator {
+    pub kenngruppe: String,
+    pub verfahrenkenngruppe: String,
+}
+
+impl Indicator {
+    pub fn new(kenngruppe: &str, verfahrenkenngruppe: &str) -> Result<Self, EnigmaError> {
+        for trigram in [kenngruppe, verfahrenkenngruppe] {
+            if trigram.len() != 3 || !trigram.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err(EnigmaError::InvalidIndicator(trigram.to_string()));
+            }
+        }
+
+        Ok(Indicator {
+            kenngruppe: kenngruppe.to_uppercase(),
+            verfahrenkenngruppe: verfahrenkenngruppe.to_uppercase(),
+        })
+    }
+
+    /// The two groups sent for the indicator. The trigrams are written one
+    /// above the other, offset by a filler letter each,
+    ///
+    /// ```text
+    /// Q V F X
+    /// M P S Y
+    /// ```
+    ///
+    /// and each column swapped in `table`, the top row giving the first
+    /// group and the bottom the second.
+    pub fn encode(&self, table: &BigramTable, fillers: [char; 2]) -> Result<String, EnigmaError> {
+        let top = fillers[0].to_string() + &self.kenngruppe;
+        let bottom = self.verfahrenkenngruppe.to_string() + &fillers[1].to_string();
+
+        let (mut first, mut second) = (String::new(), String::new());
+        for (a, b) in top.chars().zip(bottom.chars()) {
+            let (x, y) = table.swap(a, b)?;
+            first.push(x);
+            second.push(y);
+        }
+
+        Ok(first + " " + &second)
+    }
+
+    /// The trigrams back out of the two indicator groups.
+    pub fn decode(groups: &str, table: &BigramTable) -> Result<Self, EnigmaError> {
+        let invalid = || EnigmaError::InvalidIndicator(groups.to_string());
+        let letters = groups.split_whitespace().collect::<String>();
+        if letters.len() != 8 || !letters.is_ascii() {
+            return Err(invalid());
+        }
+
+        let (first, second) = letters.split_at(4);
+        let (mut top, mut bottom) = (String::new(), String::new());
+        for (a, b) in first.chars().zip(second.chars()) {
+            let (x, y) = table.swap(a, b).map_err(|_| invalid())?;
+            top.push(x);
+            bottom.push(y);
+        }
+
+        Indicator::new(&top[1..], &bottom[..3])
+    }
+
+    /// The message as sent: the indicator groups, the ciphertext in groups
+    /// of four and the indicator groups again. Whitespace in `plaintext` is
+    /// dropped.
+    pub fn encipher(
+        &self,
+        key: &DailyKey,
+        table: &BigramTable,
+        fillers: [char; 2],
+        plaintext: &str,
+    ) -> Result<String, EnigmaError> {
+        let indicator = self.encode(table, fillers)?;
+        let message_key = self.message_key(key)?;
+
+        let plaintext = plaintext.split_whitespace().collect::<String>();
+        let ciphertext = key.enigma(Some(&message_key))?.try_encode(&plaintext)?;
+        let groups = ciphertext
+            .as_bytes()
+            .chunks(4)
+            .map(|group| String::from_utf8_lossy(group))
+            .collect::<Vec<_>>();
+
+        Ok([indicator.as_str(), &groups.join(" "), &indicator].join(" "))
+    }
+
+    /// The indicator and plaintext of a message as received, which has to
+    /// end with the same indicator groups it starts with.
+    pub fn decipher(
+        key: &DailyKey,
+        table: &BigramTable,
+        message: &str,
+    ) -> Result<(Self, String), EnigmaError> {
+        let letters = message.split_whitespace().collect::<String>();
+        if letters.len() < 16 || !letters.is_ascii() || letters[..8] != letters[letters.len() - 8..]
+        {
+            return Err(EnigmaError::InvalidIndicator(message.to_string()));
+        }
+
+        let indicator = Indicator::decode(&letters[..8], table)?;
+        let message_key = indicator.message_key(key)?;
+        let plaintext = key
+            .enigma(Some(&message_key))?
+            .try_encode(&letters[8..letters.len() - 8])?;
+
+        Ok((indicator, plaintext))
+    }
+
+    /// The Verfahrenkenngruppe enciphered at the Grundstellung sets the three
+    /// rotors; a Greek wheel stays where the Grundstellung has it.
+    fn message_key(&self, key: &DailyKey) -> Result<String, EnigmaError> {
+        let ground = key
+            .grundstellung
+            .as_deref()
+            .ok_or(EnigmaError::NoGrundstellung)?;
+        let rotors = key
+            .enigma(Some(ground))?
+            .try_encode(&self.verfahrenkenngruppe)?;
+
+        // the Grundstellung may be written as numbers or with dashes, so the
+        // Greek wheel's letter is read off it rather than sliced out
+        let greek = match settings(ground) {
+            Some(positions) if positions.len() >= 3 => positions[..positions.len() - 3]
+                .iter()
+                .map(|p| (b'A' + *p as u8) as char)
+                .collect::<String>(),
+            _ => return Err(EnigmaError::InvalidPosition(ground.to_string())),
+        };
+
+        Ok(greek + &rotors)
+    }
+}
+
+fn bigram(s: &str) -> Result<u16, EnigmaError> {
+    let letters = s.to_ascii_uppercase().into_bytes();
+    match letters[..] {
+        [a, b] if a.is_ascii_uppercase() && b.is_ascii_uppercase() => {
+            Ok((a - b'A') as u16 * 26 + (b - b'A') as u16)
+        }
+        _ => Err(EnigmaError::InvalidBigramTable(s.to_string())),
+    }
+}
+
+fn letter(x: u16) -> char {
+    (x as u8 + b'A') as char
+}
+
+/// A table swapping the first letter of each bigram thirteen on, AA=NA.
+#[cfg(test)]
+fn table() -> BigramTable {
+    let mut s = String::from("# test table\n");
+    for a in b'A'..=b'M' {
+        for b in b'A'..=b'Z' {
+            s += &format!(
+                "{}{}={}{}\n",
+                a as char,
+                b as char,
+                (a + 13) as char,
+                b as char
+            );
+        }
+    }
+
+    s.parse().unwrap()
+}
+
+#[test]
+pub fn load_bigram_table() {
+    let table = table();
+    assert_eq!(table.swap('a', 'x'), Ok(('N', 'X')));
+    assert_eq!(table.swap('N', 'X'), Ok(('A', 'X')));
+    assert_eq!(table.to_string().parse(), Ok(table.clone()));
+    assert_eq!(
+        table.to_string().lines().next(),
+        Some("AA=NA AB=NB AC=NC AD=ND AE=NE AF=NF AG=NG AH=NH AI=NI AJ=NJ AK=NK AL=NL AM=NM")
+    );
+    assert_eq!(
+        table.swap('A', '1'),
+        Err(EnigmaError::InvalidBigramTable("A1".to_string()))
+    );
+
+    let text = table.to_string();
+    assert_eq!(
+        BigramTable::from_str(&text.replace("AA=NA", "AA=NB")),
+        Err(EnigmaError::InvalidBigramTable("AB=NB".to_string()))
+    );
+    assert_eq!(
+        BigramTable::from_str(&text.replace("AA=NA", "")),
+        Err(EnigmaError::InvalidBigramTable(
+            "2 bigrams unpaired".to_string()
+        ))
+    );
+    assert_eq!(
+        BigramTable::from_str(&text.replace("AA=NA", "AA=AA")),
+        Err(EnigmaError::InvalidBigramTable("AA=AA".to_string()))
+    );
+    assert_eq!(
+        BigramTable::from_str("AA-NA"),
+        Err(EnigmaError::InvalidBigramTable("AA-NA".to_string()))
+    );
+}
+
+#[test]
+pub fn encipher_naval_message() {
+    use crate::keysheet::KeySheet;
+
+    let table = table();
+    let indicator = Indicator::new("vfx", "MPS").unwrap();
+    // QM VP FS XY swap to DM IP SS KY
+    assert_eq!(
+        indicator.encode(&table, ['Q', 'Y']),
+        Ok("DISK MPSY".to_string())
+    );
+    assert_eq!(
+        Indicator::decode("DISK MPSY", &table),
+        Ok(indicator.clone())
+    );
+    assert_eq!(
+        Indicator::new("VF", "MPS"),
+        Err(EnigmaError::InvalidIndicator("VF".to_string()))
+    );
+
+    let sheet = KeySheet::from_str(
+        "Datum | Umkehrwalze | Walzenlage   | Ringstellung | Steckerverbindungen           | Grundstellung\n\
+         1     | B           | Beta II IV I | A A A V      | AT BL DF GJ HM NW OP QY RZ VX | VJNA\n\
+         2     | C           | Gamma V I II | A A A A      |                               |",
+    )
+    .unwrap();
+    let key = sheet.day(1).unwrap();
+
+    let message = indicator
+        .encipher(key, &table, ['Q', 'Y'], "VONV ONJL OOKS JHFF TTTE INSE")
+        .unwrap();
+    assert!(message.starts_with("DISK MPSY "));
+    assert!(message.ends_with(" DISK MPSY"));
+
+    // the greek wheel stays at V, the rest come off MPS at VJNA
+    let rotors = key.enigma(None).unwrap().encode("MPS");
+    let ciphertext = key
+        .enigma(Some(&format!("V{}", rotors)))
+        .unwrap()
+        .encode("VONVONJLOOKSJHFFTTTEINSE");
+    assert_eq!(message[10..message.len() - 10].replace(' ', ""), ciphertext);
+    assert_eq!(message.split(' ').nth(2).map(str::len), Some(4));
+
+    assert_eq!(
+        Indicator::decipher(key, &table, &message),
+        Ok((indicator.clone(), "VONVONJLOOKSJHFFTTTEINSE".to_string()))
+    );
+    assert_eq!(
+        Indicator::decipher(key, &table, &message[..message.len() - 1]),
+        Err(EnigmaError::InvalidIndicator(
+            message[..message.len() - 1].to_string()
+        ))
+    );
+
+    assert_eq!(
+        indicator.encipher(sheet.day(2).unwrap(), &table, ['Q', 'Y'], "VONV"),
+        Err(EnigmaError::NoGrundstellung)
+    );
+
+    // the same Grundstellung written with dashes or as numbers
+    for ground in ["V-J-N-A", "22 10 14 01"] {
+        let mut key = key.clone();
+        key.grundstellung = Some(ground.to_string());
+        assert_eq!(
+            Indicator::decipher(&key, &table, &message),
+            Ok((indicator.clone(), "VONVONJLOOKSJHFFTTTEINSE".to_string()))
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::EnigmaError, util::pairs};
+
+/// The plugboard (Steckerbrett): up to thirteen cables, each swapping a pair
+/// of letters on the way into the rotors and again on the way out. Saved as
+/// its pairs, "AV BS CG".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Plugboard {
+    wiring: [u8; 26],
+}
+
+impl Plugboard {
+    /// A plugboard with no cables in.
+    pub fn new() -> Self {
+        let mut wiring = [0; 26];
+        for (i, contact) in wiring.iter_mut().enumerate() {
+            *contact = i as u8;
+        }
+
+        Self { wiring }
+    }
+
+    /// Plugs in a cable per pair. A letter can only take one cable, and a
+    /// cable can't join a letter to itself.
+    pub fn from_pairs(pairs: &[(char, char)]) -> Result<Self, EnigmaError> {
+        cables(pairs.len())?;
+
+        let mut plugboard = Plugboard::new();
+        for &(a, b) in pairs {
+            plugboard.connect(a, b)?;
+        }
+
+        Ok(plugboard)
+    }
+
+    pub fn connect(&mut self, a: char, b: char) -> Result<(), EnigmaError> {
+        let (x, y) = (a.to_ascii_uppercase(), b.to_ascii_uppercase());
+        if !x.is_ascii_uppercase() || !y.is_ascii_uppercase() || x == y {
+            return Err(EnigmaError::InvalidPlugPair(a, b));
+        }
+
+        self.plug(x as u8 - b'A', y as u8 - b'A')
+    }
+
+    /// Plugs a cable between two letter offsets.
+    fn plug(&mut self, x: u8, y: u8) -> Result<(), EnigmaError> {
+        if x == y {
+            let letter = (x + b'A') as char;
+            return Err(EnigmaError::InvalidPlugPair(letter, letter));
+        }
+
+        for letter in [x, y] {
+            if self.wiring[letter as usize] != letter {
+                return Err(EnigmaError::DuplicatePlug((letter + b'A') as char));
+            }
+        }
+
+        self.wiring[x as usize] = y;
+        self.wiring[y as usize] = x;
+        Ok(())
+    }
+
+    /// The cables in, each with its letters in order and the lot sorted.
+    pub fn pairs(&self) -> Vec<(char, char)> {
+        self.wiring
+            .iter()
+            .enumerate()
+            .filter(|(i, out)| *i < **out as usize)
+            .map(|(i, out)| ((i as u8 + b'A') as char, (out + b'A') as char))
+            .collect()
+    }
+
+    /// The letter each contact is swapped for, 0-25.
+    pub(crate) fn wiring(&self) -> [u8; 26] {
+        self.wiring
+    }
+}
+
+impl Default for Plugboard {
+    fn default() -> Self {
+        Plugboard::new()
+    }
+}
+
+/// Reads the pairs as written on a key sheet, "AV BS CG" or "AV-BS-CG".
+impl FromStr for Plugboard {
+    type Err = EnigmaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pairs = pairs(s)?;
+        cables(pairs.len())?;
+
+        let mut plugboard = Plugboard::new();
+        for (x, y) in pairs {
+            plugboard.plug(x, y)?;
+        }
+
+        Ok(plugboard)
+    }
+}
+
+/// There are only letters enough for thirteen cables.
+fn cables(n: usize) -> Result<(), EnigmaError> {
+    match n > 13 {
+        true => Err(EnigmaError::InvalidPlugboard(format!("{} cables", n))),
+        false => Ok(()),
+    }
+}
+
+impl From<Plugboard> for String {
+    fn from(x: Plugboard) -> Self {
+        x.to_string()
+    }
+}
+
+impl TryFrom<String> for Plugboard {
+    type Error = EnigmaError;
+
+    fn try_from(x: String) -> Result<Self, Self::Error> {
+        Plugboard::from_str(&x)
+    }
+}
+
+impl fmt::Display for Plugboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pairs = self
+            .pairs()
+            .iter()
+            .map(|(a, b)| format!("{}{}", a, b))
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", pairs.join(" "))
+    }
+}
+
+#[test]
+pub fn parse_plugboard() {
+    let plugboard = Plugboard::from_str("rx AV-BS cg").unwrap();
+    assert_eq!(plugboard.to_string(), "AV BS CG RX");
+    assert_eq!(plugboard.pairs()[3], ('R', 'X'));
+    assert_eq!(Plugboard::from_str("AV BS CG RX"), Ok(plugboard));
+    assert_eq!(Plugboard::from_str("").unwrap().to_string(), "");
+
+    assert_eq!(
+        Plugboard::from_str("AB AC"),
+        Err(EnigmaError::DuplicatePlug('A'))
+    );
+    assert_eq!(
+        Plugboard::from_str("AB CB"),
+        Err(EnigmaError::DuplicatePlug('B'))
+    );
+    assert_eq!(
+        Plugboard::from_str("AA"),
+        Err(EnigmaError::InvalidPlugPair('A', 'A'))
+    );
+    assert_eq!(
+        Plugboard::from_str("A1"),
+        Err(EnigmaError::InvalidPlugPair('A', '1'))
+    );
+    assert_eq!(
+        Plugboard::from_str("AB CDE"),
+        Err(EnigmaError::InvalidPlugboard("CDE".to_string()))
+    );
+    assert_eq!(
+        Plugboard::from_str("AB CD EF GH IJ KL MN OP QR ST UV WX YZ AC"),
+        Err(EnigmaError::InvalidPlugboard("14 cables".to_string()))
+    );
+}
@@ -0,0 +1,186 @@
+use crate::{error::EnigmaError, keysheet::DailyKey};
+
+/// How the message key reaches the receiver. Either way the operator picks
+/// a message key, one letter per rotor, enciphers it for the indicator and
+/// then turns the rotors to it to encipher the message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Procedure {
+    /// Until May 1940: the message key typed twice at the day's
+    /// Grundstellung, sent as "PKPJXI".
+    Doubled,
+    /// From May 1940: a Grundstellung of the operator's own choosing sent in
+    /// clear, then the message key typed once at it, as "WXC KCH".
+    Clear,
+}
+
+impl Procedure {
+    /// The message as transmitted, its indicator followed by the ciphertext.
+    /// `ground` is the Grundstellung the message key is enciphered at; the
+    /// doubled procedure falls back on the key sheet's.
+    pub fn encipher(
+        &self,
+        key: &DailyKey,
+        ground: Option<&str>,
+        message_key: &str,
+        plaintext: &str,
+    ) -> Result<String, EnigmaError> {
+        let len = key.rotors.len();
+        if message_key.len() != len || !message_key.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(EnigmaError::InvalidPosition(message_key.to_string()));
+        }
+
+        let ground = self.ground(key, ground)?;
+        let mut enigma = key.enigma(Some(ground))?;
+        let indicator = match self {
+            Procedure::Doubled => enigma.try_encode(&message_key.repeat(2))?,
+            Procedure::Clear => ground.to_uppercase() + " " + &enigma.try_encode(message_key)?,
+        };
+
+        let ciphertext = key.enigma(Some(message_key))?.try_encode(plaintext)?;
+
+        Ok(indicator + " " + &ciphertext)
+    }
+
+    /// The plaintext of a message as received, indicator first. For the
+    /// doubled procedure both copies of the message key have to agree.
+    pub fn decipher(&self, key: &DailyKey, message: &str) -> Result<String, EnigmaError> {
+        let len = key.rotors.len();
+        let (indicator, ciphertext) = split(message, 2 * len)?;
+
+        let message_key = match self {
+            Procedure::Doubled => {
+                let ground = self.ground(key, None)?;
+                let doubled = key.enigma(Some(ground))?.try_encode(&indicator)?;
+                if doubled[..len] != doubled[len..] {
+                    return Err(EnigmaError::InvalidIndicator(indicator));
+                }
+
+                doubled[..len].to_string()
+            }
+            Procedure::Clear => key
+                .enigma(Some(&indicator[..len]))?
+                .try_encode(&indicator[len..])?,
+        };
+
+        key.enigma(Some(&message_key))?.try_encode(ciphertext)
+    }
+
+    fn ground<'a>(
+        &self,
+        key: &'a DailyKey,
+        ground: Option<&'a str>,
+    ) -> Result<&'a str, EnigmaError> {
+        let ground = match self {
+            Procedure::Doubled => ground.or(key.grundstellung.as_deref()),
+            Procedure::Clear => ground,
+        };
+
+        ground.ok_or(EnigmaError::NoGrundstellung)
+    }
+}
+
+/// Takes the first `len` letters off a message, whatever spaces they're
+/// grouped with, leaving the rest.
+fn split(message: &str, len: usize) -> Result<(String, &str), EnigmaError> {
+    let message = message.trim_start();
+    let mut indicator = String::with_capacity(len);
+
+    for (offset, ch) in message.char_indices() {
+        if indicator.len() == len {
+            return Ok((indicator, message[offset..].trim_start()));
+        }
+
+        match ch {
+            c if c.is_ascii_alphabetic() => indicator.push(c.to_ascii_uppercase()),
+            c if c.is_whitespace() => continue,
+            _ => break,
+        }
+    }
+
+    match indicator.len() == len {
+        true => Ok((indicator, "")),
+        false => Err(EnigmaError::InvalidIndicator(message.to_string())),
+    }
+}
+
+#[cfg(test)]
+use std::str::FromStr;
+
+#[cfg(test)]
+use crate::keysheet::KeySheet;
+
+#[test]
+pub fn encipher_w_clear_indicator() {
+    // the Barbarossa message of 7 July 1941
+    let sheet = KeySheet::from_str(
+        "Datum | Walzenlage | Ringstellung | Steckerverbindungen\n\
+         7     | II IV V    | 02 21 12     | AV BS CG DL FU HZ IN KM OW RX",
+    )
+    .unwrap();
+    let key = sheet.day(7).unwrap();
+
+    assert_eq!(
+        Procedure::Clear.decipher(key, "WXC KCH EDPUD NRGYS ZRCXN UYTPO MRMBO"),
+        Ok("AUFKL XABTE ILUNG XVONX KURTI".to_string())
+    );
+    assert_eq!(
+        Procedure::Clear.encipher(key, Some("wxc"), "BLA", "AUFKL XABTE ILUNG XVONX KURTI"),
+        Ok("WXC KCH EDPUD NRGYS ZRCXN UYTPO MRMBO".to_string())
+    );
+
+    assert_eq!(
+        Procedure::Clear.encipher(key, None, "BLA", "AUFKL"),
+        Err(EnigmaError::NoGrundstellung)
+    );
+    assert_eq!(
+        Procedure::Clear.encipher(key, Some("WXC"), "BL", "AUFKL"),
+        Err(EnigmaError::InvalidPosition("BL".to_string()))
+    );
+    assert_eq!(
+        Procedure::Clear.decipher(key, "WXC KC"),
+        Err(EnigmaError::InvalidIndicator("WXC KC".to_string()))
+    );
+}
+
+#[test]
+pub fn encipher_w_doubled_indicator() {
+    let sheet = KeySheet::from_str(
+        "Datum | Umkehrwalze | Walzenlage | Ringstellung | Steckerverbindungen | Grundstellung\n\
+         1     | B           | II I III   | 24 13 22     | AM FI NV PS TU WZ   | FOL\n\
+         2     | C           | I II III   | 01 01 01     |                     |",
+    )
+    .unwrap();
+    let key = sheet.day(1).unwrap();
+
+    let message = Procedure::Doubled
+        .encipher(key, None, "ABL", "FEIND LICHE INFAN TERIE")
+        .unwrap();
+    let mut enigma = key.enigma(None).unwrap();
+    assert_eq!(message[..6], enigma.encode("ABLABL"));
+    assert_eq!(
+        message[7..],
+        key.enigma(Some("ABL"))
+            .unwrap()
+            .encode("FEIND LICHE INFAN TERIE")
+    );
+    assert_eq!(
+        Procedure::Doubled.decipher(key, &message),
+        Ok("FEIND LICHE INFAN TERIE".to_string())
+    );
+
+    // a garbled indicator no longer doubles up
+    let garbled = format!("A{}", &message[1..]);
+    assert_eq!(
+        Procedure::Doubled.decipher(key, &garbled),
+        Err(EnigmaError::InvalidIndicator(garbled[..6].to_string()))
+    );
+
+    let key = sheet.day(2).unwrap();
+    assert_eq!(
+        Procedure::Doubled.encipher(key, None, "ABL", "FEIND"),
+        Err(EnigmaError::NoGrundstellung)
+    );
+    assert!(Procedure::Doubled
+        .encipher(key, Some("QRS"), "ABL", "FEIND")
+        .is_ok());
+}
@@ -1,7 +1,14 @@
+use std::{fmt, str::FromStr};
 
-use strum_macros::EnumString;
+use serde::{Deserialize, Serialize};
+use strum_macros::{AsRefStr, EnumString};
 
-#[derive(Debug, Clone, EnumString, PartialEq)]
+use crate::{error::EnigmaError, model::Model};
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, AsRefStr, EnumString, PartialEq, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(into = "Spec", try_from = "Spec")]
 pub enum Rotor {
     #[strum(ascii_case_insensitive)]
     I,
@@ -23,9 +30,68 @@ pub enum Rotor {
     BETA,
     #[strum(ascii_case_insensitive)]
     GAMMA,
+    #[strum(to_string = "d-i", ascii_case_insensitive)]
+    DI,
+    #[strum(to_string = "d-ii", ascii_case_insensitive)]
+    DII,
+    #[strum(to_string = "d-iii", ascii_case_insensitive)]
+    DIII,
+    #[strum(to_string = "k-i", ascii_case_insensitive)]
+    KI,
+    #[strum(to_string = "k-ii", ascii_case_insensitive)]
+    KII,
+    #[strum(to_string = "k-iii", ascii_case_insensitive)]
+    KIII,
+    #[strum(to_string = "swiss-k-i", ascii_case_insensitive)]
+    SWISSKI,
+    #[strum(to_string = "swiss-k-ii", ascii_case_insensitive)]
+    SWISSKII,
+    #[strum(to_string = "swiss-k-iii", ascii_case_insensitive)]
+    SWISSKIII,
+    #[strum(to_string = "railway-i", ascii_case_insensitive)]
+    RAILWAYI,
+    #[strum(to_string = "railway-ii", ascii_case_insensitive)]
+    RAILWAYII,
+    #[strum(to_string = "railway-iii", ascii_case_insensitive)]
+    RAILWAYIII,
+    #[strum(to_string = "g-312-i", ascii_case_insensitive)]
+    G312I,
+    #[strum(to_string = "g-312-ii", ascii_case_insensitive)]
+    G312II,
+    #[strum(to_string = "g-312-iii", ascii_case_insensitive)]
+    G312III,
+    #[strum(to_string = "g-260-i", ascii_case_insensitive)]
+    G260I,
+    #[strum(to_string = "g-260-ii", ascii_case_insensitive)]
+    G260II,
+    #[strum(to_string = "g-260-iii", ascii_case_insensitive)]
+    G260III,
+    #[strum(to_string = "t-i", ascii_case_insensitive)]
+    TI,
+    #[strum(to_string = "t-ii", ascii_case_insensitive)]
+    TII,
+    #[strum(to_string = "t-iii", ascii_case_insensitive)]
+    TIII,
+    #[strum(to_string = "t-iv", ascii_case_insensitive)]
+    TIV,
+    #[strum(to_string = "t-v", ascii_case_insensitive)]
+    TV,
+    #[strum(to_string = "t-vi", ascii_case_insensitive)]
+    TVI,
+    #[strum(to_string = "t-vii", ascii_case_insensitive)]
+    TVII,
+    #[strum(to_string = "t-viii", ascii_case_insensitive)]
+    TVIII,
+    /// A rotor wired to order, built with `Rotor::custom` or found in a
+    /// `Catalog`.
+    #[strum(disabled)]
+    Custom(Custom),
 }
 
-#[derive(Debug, Default, Clone, EnumString, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Default, Clone, EnumString, PartialEq, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(into = "Spec", try_from = "Spec")]
 pub enum Reflector {
     #[default]
     #[strum(ascii_case_insensitive)]
@@ -36,10 +102,80 @@ pub enum Reflector {
     BTHIN,
     #[strum(ascii_case_insensitive)]
     CTHIN,
+    /// The Luftwaffe's field rewirable Umkehrwalze D, built with
+    /// `Reflector::ukwd` from the day's plug pairs.
+    #[strum(disabled)]
+    UKWD(Custom),
+    #[strum(serialize = "d-ukw", ascii_case_insensitive)]
+    DUKW,
+    #[strum(serialize = "k-ukw", ascii_case_insensitive)]
+    KUKW,
+    #[strum(serialize = "swiss-k-ukw", ascii_case_insensitive)]
+    SWISSKUKW,
+    #[strum(serialize = "railway-ukw", ascii_case_insensitive)]
+    RAILWAYUKW,
+    #[strum(serialize = "g-312-ukw", ascii_case_insensitive)]
+    G312UKW,
+    #[strum(serialize = "g-260-ukw", ascii_case_insensitive)]
+    G260UKW,
+    #[strum(serialize = "t-ukw", ascii_case_insensitive)]
+    TUKW,
+    /// A reflector wired to order, built with `Reflector::custom` or found
+    /// in a `Catalog`.
+    #[strum(disabled)]
+    Custom(Custom),
+}
+
+/// The name, wiring and notches of a wheel wired to order. They're checked
+/// when the wheel is built, so there's no way to make one directly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Custom {
+    name: String,
+    alpha: String,
+    turnovers: String,
+}
+
+impl Custom {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 }
 
+/// Letter labels for the UKW-D plug pairs. Bletchley Park named the contacts
+/// after the entry wheel letters they face, the Germans lettered the sockets
+/// themselves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Notation {
+    Bletchley,
+    German,
+}
+
+/// The 24 UKW-D sockets in German lettering and the contact each one is in
+/// Bletchley lettering. The remaining pair, J-Y (German) or B-O (Bletchley),
+/// is wired inside the reflector and can't be changed.
+const UKWD_GERMAN: &str = "ABCDEFGHIKLMNOPQRSTUVWXZ";
+const UKWD_BLETCHLEY: &str = "AZYXWVUTSRQPNMLKJIHGFEDC";
+
 impl Rotor {
-    pub fn alpha(&self) -> &'static str {
+    /// Wires a rotor to order from the letters contacts A to Z come out on
+    /// and the window letters it turns the next rotor over at.
+    pub fn custom(name: &str, wiring: &str, notches: &str) -> Result<Self, EnigmaError> {
+        let invalid = || EnigmaError::InvalidWiring(name.to_string());
+        let alpha = wiring.to_ascii_uppercase();
+        let turnovers = notches.to_ascii_uppercase();
+
+        if !permutation(&alpha) || !turnovers.bytes().all(|n| n.is_ascii_uppercase()) {
+            return Err(invalid());
+        }
+
+        Ok(Rotor::Custom(Custom {
+            name: name.to_string(),
+            alpha,
+            turnovers,
+        }))
+    }
+
+    pub fn alpha(&self) -> &str {
         match self {
             Rotor::I => "EKMFLGDQVZNTOWYHXUSPAIBRCJ",
             Rotor::II => "AJDKSIRUXBLHWTMCQGZNPYFVOE",
@@ -51,10 +187,34 @@ impl Rotor {
             Rotor::VIII => "FKQHTLXOCBJSPDZRAMEWNIUYGV",
             Rotor::BETA => "LEYJVCNIXWPBQMDRTAKZGFUHOS",
             Rotor::GAMMA => "FSOKANUERHMBTIYCWLQPZXVGJD",
+            Rotor::DI | Rotor::KI => "LPGSZMHAEOQKVXRFYBUTNICJDW",
+            Rotor::DII | Rotor::KII => "SLVGBTFXJQOHEWIRZYAMKPCNDU",
+            Rotor::DIII | Rotor::KIII => "CJGDPSHKTURAWZXFMYNQOBVLIE",
+            Rotor::SWISSKI => "PEZUOHXSCVFMTBGLRINQJWAYDK",
+            Rotor::SWISSKII => "ZOUESYDKFWPCIQXHMVBLGNJRAT",
+            Rotor::SWISSKIII => "EHRVXGAOBQUSIMZFLYNWKTPDJC",
+            Rotor::RAILWAYI => "JGDQOXUSCAMIFRVTPNEWKBLZYH",
+            Rotor::RAILWAYII => "NTZPSFBOKMWRCJDIVLAEYUXHGQ",
+            Rotor::RAILWAYIII => "JVIUBHTCDYAKEQZPOSGXNRMWFL",
+            Rotor::G312I => "DMTWSILRUYQNKFEJCAZBPGXOHV",
+            Rotor::G312II => "HQZGPJTMOBLNCIFDYAWVEUSRKX",
+            Rotor::G312III => "UQNTLSZFMREHDPXKIBVYGJCWOA",
+            Rotor::G260I => "RCSPBLKQAUMHWYTIFZVGOJNEXD",
+            Rotor::G260II => "WCMIBVPJXAROSGNDLZKEYHUFQT",
+            Rotor::G260III => "FVDHZELSQMAXOKYIWPGCBUJTNR",
+            Rotor::TI => "KPTYUELOCVGRFQDANJMBSWHZXI",
+            Rotor::TII => "UPHZLWEQMTDJXCAKSOIGVBYFNR",
+            Rotor::TIII => "QUDLYRFEKONVZAXWHMGPJBSICT",
+            Rotor::TIV => "CIWTBKXNRESPFLYDAGVHQUOJZM",
+            Rotor::TV => "UAXGISNJBVERDYLFZWTPCKOHMQ",
+            Rotor::TVI => "XFUZGALVHCNYSEWQTDMRBKPIOJ",
+            Rotor::TVII => "BJVFTXPLNAYOZIKWGDQERUCHSM",
+            Rotor::TVIII => "YMTPNZHWKODAJXELUQVGCBISFR",
+            Rotor::Custom(x) => &x.alpha,
         }
     }
 
-    pub fn turnovers(&self) -> &'static str {
+    pub fn turnovers(&self) -> &str {
         match self {
             Rotor::I => "Q",
             Rotor::II => "E",
@@ -66,17 +226,331 @@ impl Rotor {
             Rotor::VIII => "ZM",
             Rotor::BETA => "",
             Rotor::GAMMA => "",
+            Rotor::DI | Rotor::KI | Rotor::SWISSKI | Rotor::RAILWAYI => "Y",
+            Rotor::DII | Rotor::KII | Rotor::SWISSKII | Rotor::RAILWAYII => "E",
+            Rotor::DIII | Rotor::KIII | Rotor::SWISSKIII | Rotor::RAILWAYIII => "N",
+            Rotor::G312I | Rotor::G260I => "SUVWZABCEFGIKLOPQ",
+            Rotor::G312II | Rotor::G260II => "STVYZACDFGHKMNQ",
+            Rotor::G312III | Rotor::G260III => "UWXAEFHKMNR",
+            Rotor::TI => "WZEKQ",
+            Rotor::TII => "WZFLR",
+            Rotor::TIII => "WZEKQ",
+            Rotor::TIV => "WZFLR",
+            Rotor::TV => "YCFKR",
+            Rotor::TVI => "XEIMQ",
+            Rotor::TVII => "YCFKR",
+            Rotor::TVIII => "XEIMQ",
+            Rotor::Custom(x) => &x.turnovers,
         }
     }
+
+    /// The machine the rotor was made for. Greek wheels belong to the M4, the
+    /// other military rotors to the M3, though an M4 takes them too. Custom
+    /// rotors go in any machine.
+    pub fn model(&self) -> Option<Model> {
+        let model = match self {
+            Rotor::BETA | Rotor::GAMMA => Model::M4,
+            Rotor::DI | Rotor::DII | Rotor::DIII => Model::D,
+            Rotor::KI | Rotor::KII | Rotor::KIII => Model::K,
+            Rotor::SWISSKI | Rotor::SWISSKII | Rotor::SWISSKIII => Model::SwissK,
+            Rotor::RAILWAYI | Rotor::RAILWAYII | Rotor::RAILWAYIII => Model::Railway,
+            Rotor::G312I | Rotor::G312II | Rotor::G312III => Model::G312,
+            Rotor::G260I | Rotor::G260II | Rotor::G260III => Model::G260,
+            Rotor::TI
+            | Rotor::TII
+            | Rotor::TIII
+            | Rotor::TIV
+            | Rotor::TV
+            | Rotor::TVI
+            | Rotor::TVII
+            | Rotor::TVIII => Model::T,
+            Rotor::Custom(_) => return None,
+            _ => Model::M3,
+        };
+
+        Some(model)
+    }
+}
+
+impl fmt::Display for Rotor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rotor::Custom(x) => write!(f, "{}", x.name),
+            x => write!(f, "{}", x.as_ref()),
+        }
+    }
+}
+
+/// Whether `alpha` has each letter A-Z exactly once.
+fn permutation(alpha: &str) -> bool {
+    let mut seen = 0u32;
+    for c in alpha.bytes() {
+        if !c.is_ascii_uppercase() || seen >> (c - b'A') & 1 == 1 {
+            return false;
+        }
+        seen |= 1 << (c - b'A');
+    }
+
+    alpha.len() == 26
 }
 
 impl Reflector {
-    fn alpha(&self) -> &'static str {
+    fn alpha(&self) -> &str {
         match self {
             Reflector::UKWB => "YRUHQSLDPXNGOKMIEBFZCWVJAT",
             Reflector::UKWC => "FVPJIAOYEDRZXWGCTKUQSBNMHL",
             Reflector::BTHIN => "ENKQAUYWJICOPBLMDXZVFTHRGS",
             Reflector::CTHIN => "RDOBJNTKVEHMLFCWZAXGYIPSUQ",
+            Reflector::UKWD(x) => &x.alpha,
+            Reflector::DUKW | Reflector::KUKW | Reflector::SWISSKUKW | Reflector::G260UKW => {
+                "IMETCGFRAYSQBZXWLHKDVUPOJN"
+            }
+            Reflector::RAILWAYUKW => "QYHOGNECVPUZTFDJAXWMKISRBL",
+            Reflector::G312UKW => "RULQMZJSYGOCETKWDAHNBXPVIF",
+            Reflector::TUKW => "GEKPBTAUMOCNILJDXZYFHWVQSR",
+            Reflector::Custom(x) => &x.alpha,
+        }
+    }
+
+    /// Wires a reflector to order from the letters contacts A to Z are
+    /// paired with. Every letter has to be paired with another.
+    pub fn custom(name: &str, wiring: &str) -> Result<Self, EnigmaError> {
+        let alpha = wiring.to_ascii_uppercase();
+        let paired = |alpha: &[u8]| {
+            alpha.iter().enumerate().all(|(contact, out)| {
+                let out = (out - b'A') as usize;
+                out != contact && alpha[out] - b'A' == contact as u8
+            })
+        };
+
+        if !permutation(&alpha) || !paired(alpha.as_bytes()) {
+            return Err(EnigmaError::InvalidWiring(name.to_string()));
+        }
+
+        Ok(Reflector::Custom(Custom {
+            name: name.to_string(),
+            alpha,
+            turnovers: String::new(),
+        }))
+    }
+
+    /// Wires a UKW-D from the twelve plug pairs on a key sheet, written like
+    /// "AC LP QR ..." or "AC-LP-QR-...", in either lettering.
+    pub fn ukwd(pairs: &str, notation: Notation) -> Result<Self, EnigmaError> {
+        let invalid = |x: &str| EnigmaError::InvalidReflector(x.to_string());
+        let mut alpha = [0; 26];
+        alpha[(b'B' - b'A') as usize] = b'O';
+        alpha[(b'O' - b'A') as usize] = b'B';
+
+        let tokens = pairs
+            .split([' ', '-', ','])
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>();
+
+        if tokens.len() != 12 {
+            return Err(invalid(pairs));
+        }
+
+        for token in tokens {
+            let pair = token
+                .chars()
+                .map(|c| match notation {
+                    Notation::Bletchley => UKWD_BLETCHLEY.find(c.to_ascii_uppercase()),
+                    Notation::German => UKWD_GERMAN.find(c.to_ascii_uppercase()),
+                })
+                .map(|socket| socket.map(|s| UKWD_BLETCHLEY.as_bytes()[s]))
+                .collect::<Option<Vec<_>>>();
+
+            match pair.as_deref() {
+                Some(&[a, b])
+                    if a != b
+                        && alpha[(a - b'A') as usize] == 0
+                        && alpha[(b - b'A') as usize] == 0 =>
+                {
+                    alpha[(a - b'A') as usize] = b;
+                    alpha[(b - b'A') as usize] = a;
+                }
+                _ => return Err(invalid(token)),
+            }
+        }
+
+        Ok(Reflector::UKWD(Custom {
+            name: "ukwd".to_string(),
+            alpha: alpha.iter().map(|c| *c as char).collect(),
+            turnovers: String::new(),
+        }))
+    }
+}
+
+impl fmt::Display for Reflector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reflector::UKWD(_) => write!(f, "ukwd"),
+            Reflector::DUKW => write!(f, "d-ukw"),
+            Reflector::KUKW => write!(f, "k-ukw"),
+            Reflector::SWISSKUKW => write!(f, "swiss-k-ukw"),
+            Reflector::RAILWAYUKW => write!(f, "railway-ukw"),
+            Reflector::G312UKW => write!(f, "g-312-ukw"),
+            Reflector::G260UKW => write!(f, "g-260-ukw"),
+            Reflector::TUKW => write!(f, "t-ukw"),
+            Reflector::Custom(x) => write!(f, "{}", x.name),
+            x => write!(f, "{}", format!("{:?}", x).to_lowercase()),
+        }
+    }
+}
+
+/// The entry wheel (Eintrittswalze) between the keyboard and the rotors.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, EnumString, PartialEq, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(into = "Spec", try_from = "Spec")]
+pub enum Entry {
+    /// Key A to contact A and so on, as on the military machines.
+    #[strum(ascii_case_insensitive)]
+    Identity,
+    /// The keyboard order, kept by the commercial machines and the Enigma G.
+    #[strum(ascii_case_insensitive)]
+    QWERTZU,
+    /// The Enigma T's own scrambled order.
+    #[strum(ascii_case_insensitive)]
+    Tirpitz,
+    /// An entry wheel wired to order, built with `Entry::custom`.
+    #[strum(disabled)]
+    Custom(Custom),
+}
+
+impl Entry {
+    /// Wires an entry wheel to order from the keys contacts A to Z are
+    /// wired to.
+    pub fn custom(name: &str, wiring: &str) -> Result<Self, EnigmaError> {
+        let alpha = wiring.to_ascii_uppercase();
+        if !permutation(&alpha) {
+            return Err(EnigmaError::InvalidWiring(name.to_string()));
+        }
+
+        Ok(Entry::Custom(Custom {
+            name: name.to_string(),
+            alpha,
+            turnovers: String::new(),
+        }))
+    }
+
+    /// The keys contacts A to Z are wired to.
+    pub fn alpha(&self) -> &str {
+        match self {
+            Entry::Identity => "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+            Entry::QWERTZU => "QWERTZUIOASDFGHJKPYXCVBNML",
+            Entry::Tirpitz => "KZROUQHYAIGBLWVSTDXFPNMCJE",
+            Entry::Custom(x) => &x.alpha,
+        }
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Entry::Custom(x) => write!(f, "{}", x.name),
+            x => write!(f, "{}", format!("{:?}", x).to_lowercase()),
+        }
+    }
+}
+
+/// How a wheel is written in a saved machine: by name, or with its wiring
+/// when it was made to order, as in a `Catalog`. A UKW-D goes under its own
+/// key, so a made to order reflector can be called "ukwd" too.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Spec {
+    Name(String),
+    Ukwd {
+        ukwd: String,
+    },
+    Wired {
+        name: String,
+        wiring: String,
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        notches: String,
+    },
+}
+
+impl From<Custom> for Spec {
+    fn from(x: Custom) -> Self {
+        Spec::Wired {
+            name: x.name,
+            wiring: x.alpha,
+            notches: x.turnovers,
+        }
+    }
+}
+
+impl From<Rotor> for Spec {
+    fn from(x: Rotor) -> Self {
+        match x {
+            Rotor::Custom(x) => x.into(),
+            x => Spec::Name(x.to_string()),
+        }
+    }
+}
+
+impl TryFrom<Spec> for Rotor {
+    type Error = EnigmaError;
+
+    fn try_from(x: Spec) -> Result<Self, Self::Error> {
+        match x {
+            Spec::Name(x) => Rotor::from_str(&x).map_err(|_| EnigmaError::UnknownRotor(x)),
+            Spec::Ukwd { .. } => Err(EnigmaError::UnknownRotor("ukwd".to_string())),
+            Spec::Wired {
+                name,
+                wiring,
+                notches,
+            } => Rotor::custom(&name, &wiring, &notches),
+        }
+    }
+}
+
+impl From<Reflector> for Spec {
+    fn from(x: Reflector) -> Self {
+        match x {
+            Reflector::Custom(x) => x.into(),
+            Reflector::UKWD(x) => Spec::Ukwd { ukwd: x.alpha },
+            x => Spec::Name(x.to_string()),
+        }
+    }
+}
+
+impl TryFrom<Spec> for Reflector {
+    type Error = EnigmaError;
+
+    fn try_from(x: Spec) -> Result<Self, Self::Error> {
+        match x {
+            Spec::Name(x) => Reflector::from_str(&x).map_err(|_| EnigmaError::UnknownReflector(x)),
+            // the B-O pair is fixed inside every UKW-D
+            Spec::Ukwd { ukwd } => match Reflector::custom("ukwd", &ukwd) {
+                Ok(Reflector::Custom(x)) if x.alpha.as_bytes()[1] == b'O' => Ok(Reflector::UKWD(x)),
+                _ => Err(EnigmaError::InvalidReflector(ukwd)),
+            },
+            Spec::Wired { name, wiring, .. } => Reflector::custom(&name, &wiring),
+        }
+    }
+}
+
+impl From<Entry> for Spec {
+    fn from(x: Entry) -> Self {
+        match x {
+            Entry::Custom(x) => x.into(),
+            x => Spec::Name(x.to_string()),
+        }
+    }
+}
+
+impl TryFrom<Spec> for Entry {
+    type Error = EnigmaError;
+
+    fn try_from(x: Spec) -> Result<Self, Self::Error> {
+        match x {
+            Spec::Name(x) => Entry::from_str(&x).map_err(|_| EnigmaError::UnknownEntry(x)),
+            Spec::Ukwd { .. } => Err(EnigmaError::UnknownEntry("ukwd".to_string())),
+            Spec::Wired { name, wiring, .. } => Entry::custom(&name, &wiring),
         }
     }
 }
@@ -85,6 +559,8 @@ impl Reflector {
 pub struct Rotors {
     pub reflector: Reflector,
     pub rotors: Vec<Wheel>,
+    /// The entry wheel, or the one the model was built with when `None`.
+    pub entry: Option<Entry>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -94,14 +570,14 @@ pub enum Wheel {
 }
 
 impl Wheel {
-    pub fn alpha(&self) -> &'static str {
+    pub fn alpha(&self) -> &str {
         match self {
             Wheel::Rotor(x) => x.alpha(),
             Wheel::Reflector(x) => x.alpha(),
         }
     }
 
-    pub fn turnovers(&self) -> &'static str {
+    pub fn turnovers(&self) -> &str {
         match self {
             Wheel::Rotor(x) => x.turnovers(),
             _ => panic!("There are no turnovers for reflectors"),
@@ -118,6 +594,62 @@ impl Default for Rotors {
                 Wheel::Rotor(Rotor::II),
                 Wheel::Rotor(Rotor::III),
             ],
+            entry: None,
         }
     }
 }
+
+#[test]
+pub fn wire_ukwd() {
+    let bletchley = "AZ CY DX EW FV GU HT IS JR KQ LP MN";
+    let ukwd = Reflector::ukwd(bletchley, Notation::Bletchley).unwrap();
+    assert_eq!(ukwd.alpha(), "ZOYXWVUTSRQPNMBLKJIHGFEDCA");
+
+    // the same wiring read off the German sockets
+    let german = "AB CZ DX EW FV GU HT IS KR LQ MP NO";
+    assert_eq!(Reflector::ukwd(german, Notation::German), Ok(ukwd.clone()));
+
+    let saved = serde_json::to_string(&ukwd).unwrap();
+    assert_eq!(saved, r#"{"ukwd":"ZOYXWVUTSRQPNMBLKJIHGFEDCA"}"#);
+    assert_eq!(serde_json::from_str::<Reflector>(&saved).unwrap(), ukwd);
+    // pairs, but without the fixed B-O
+    assert!(serde_json::from_str::<Reflector>(r#"{"ukwd":"YRUHQSLDPXNGOKMIEBFZCWVJAT"}"#).is_err());
+
+    for (pairs, notation, bad) in [
+        (
+            "AZ CY DX EW FV GU HT IS JR KQ LP",
+            Notation::Bletchley,
+            "AZ CY DX EW FV GU HT IS JR KQ LP",
+        ),
+        (
+            "AZ CY DX EW FV GU HT IS JR KQ LP MB",
+            Notation::Bletchley,
+            "MB",
+        ),
+        (
+            "AB CD EF GH IK LM NO PQ RS TU VW XY",
+            Notation::German,
+            "XY",
+        ),
+        (
+            "AZ CY DX EW FV GU HT IS JR KQ LP MM",
+            Notation::Bletchley,
+            "MM",
+        ),
+        (
+            "AZ CY DX EW FV GU HT IS JR KQ LP MA",
+            Notation::Bletchley,
+            "MA",
+        ),
+        (
+            "AZ CY DX EW FV GU HT IS JR KQ LP M1",
+            Notation::Bletchley,
+            "M1",
+        ),
+    ] {
+        assert_eq!(
+            Reflector::ukwd(pairs, notation),
+            Err(EnigmaError::InvalidReflector(bad.to_string()))
+        );
+    }
+}
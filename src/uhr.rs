@@ -0,0 +1,226 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::EnigmaError, util::pairs};
+
+/// Where each of the 40 contacts on the Uhr's input face comes out on the
+/// other, with the switch at 00.
+const DISC: [usize; 40] = [
+    6, 31, 4, 29, 18, 39, 16, 25, 30, 23, 28, 1, 38, 11, 36, 37, 26, 27, 24, 21, 14, 3, 12, 17, 2,
+    7, 0, 33, 10, 35, 8, 5, 22, 19, 20, 13, 34, 15, 32, 9,
+];
+
+/// The contact the large pin of each b-plug sits at. Its small pin, like an
+/// a-plug's, is two contacts on; the a-plugs take every fourth from 0.
+const B_PINS: [usize; 10] = [4, 16, 28, 36, 24, 12, 0, 8, 20, 32];
+
+/// The Luftwaffe's Enigma Uhr, a switch box plugged in with ten cables in
+/// place of the plugboard's. Plug 1a to 10a go in the first letter of each
+/// pair and 1b to 10b in the second; turning the switch rewires them, and
+/// only at settings that are a multiple of four is the result reciprocal.
+/// At 00 it's the same as ten ordinary cables.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "Spec", try_from = "Spec")]
+pub struct Uhr {
+    pairs: [(u8, u8); 10],
+    setting: usize,
+}
+
+impl Uhr {
+    /// Plugs in the ten "a/b" pairs from the key sheet, in plug number
+    /// order, with the switch at `setting` (00-39).
+    pub fn new(pairs: &[(char, char)], setting: usize) -> Result<Self, EnigmaError> {
+        let invalid = || EnigmaError::InvalidUhr(format!("{:?}", pairs));
+
+        let offsets = pairs
+            .iter()
+            .map(
+                |(a, b)| match (a.to_ascii_uppercase(), b.to_ascii_uppercase()) {
+                    (x, y) if x.is_ascii_uppercase() && y.is_ascii_uppercase() => {
+                        Some((x as u8 - b'A', y as u8 - b'A'))
+                    }
+                    _ => None,
+                },
+            )
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+
+        let mut uhr = Uhr::wire(&offsets).ok_or_else(invalid)?;
+        uhr.set(setting)?;
+        Ok(uhr)
+    }
+
+    /// Plugs in ten pairs of letter offsets, with the switch at 00, if each
+    /// letter takes one plug.
+    fn wire(offsets: &[(u8, u8)]) -> Option<Self> {
+        if offsets.len() != 10 {
+            return None;
+        }
+
+        let mut uhr = Uhr {
+            pairs: [(0, 0); 10],
+            setting: 0,
+        };
+        let mut plugged = 0u32;
+
+        for (plug, &(x, y)) in uhr.pairs.iter_mut().zip(offsets) {
+            if x == y || plugged >> x & 1 == 1 || plugged >> y & 1 == 1 {
+                return None;
+            }
+
+            plugged |= 1 << x | 1 << y;
+            *plug = (x, y);
+        }
+
+        Some(uhr)
+    }
+
+    /// The pairs plugged in, in plug number order.
+    pub fn pairs(&self) -> Vec<(char, char)> {
+        self.pairs
+            .iter()
+            .map(|(a, b)| ((a + b'A') as char, (b + b'A') as char))
+            .collect()
+    }
+
+    pub fn setting(&self) -> usize {
+        self.setting
+    }
+
+    /// Turns the switch, as was done for each message.
+    pub fn set(&mut self, setting: usize) -> Result<(), EnigmaError> {
+        if setting >= 40 {
+            return Err(EnigmaError::InvalidUhr(format!("{:02}", setting)));
+        }
+
+        self.setting = setting;
+        Ok(())
+    }
+
+    /// The letter each key is sent on to the entry wheel as, for the twenty
+    /// letters plugged into the Uhr. The lamps are lit the inverse way.
+    pub(crate) fn wiring(&self) -> [Option<u8>; 26] {
+        let s = self.setting;
+        let mut inverse = [0; 40];
+        for (contact, out) in DISC.iter().enumerate() {
+            inverse[*out] = contact;
+        }
+
+        let mut wiring = [None; 26];
+        for (i, &(a, _)) in self.pairs.iter().enumerate() {
+            // in at the a-plug's large pin, out at a b-plug's small one
+            let contact = (DISC[(4 * i + s) % 40] + 40 - s) % 40;
+            let plug = B_PINS.iter().position(|p| p + 2 == contact).unwrap();
+            wiring[a as usize] = Some(self.pairs[plug].1);
+        }
+
+        for (j, &(_, b)) in self.pairs.iter().enumerate() {
+            // in at the b-plug's large pin, back out at an a-plug's small one
+            let contact = (inverse[(B_PINS[j] + s) % 40] + 40 - s) % 40;
+            wiring[b as usize] = Some(self.pairs[(contact - 2) / 4].0);
+        }
+
+        wiring
+    }
+}
+
+/// A saved Uhr, its pairs written "AW BX CY ..." in plug number order.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Spec {
+    pairs: String,
+    setting: usize,
+}
+
+impl From<Uhr> for Spec {
+    fn from(x: Uhr) -> Self {
+        let pairs = x
+            .pairs()
+            .iter()
+            .map(|(a, b)| format!("{}{}", a, b))
+            .collect::<Vec<_>>();
+
+        Spec {
+            pairs: pairs.join(" "),
+            setting: x.setting,
+        }
+    }
+}
+
+impl TryFrom<Spec> for Uhr {
+    type Error = EnigmaError;
+
+    fn try_from(x: Spec) -> Result<Self, Self::Error> {
+        let mut uhr = Uhr::from_str(&x.pairs)?;
+        uhr.set(x.setting)?;
+        Ok(uhr)
+    }
+}
+
+/// Reads the ten pairs as written on a key sheet, "AW BX CY ..." in plug
+/// number order, with the switch at 00.
+impl FromStr for Uhr {
+    type Err = EnigmaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || EnigmaError::InvalidUhr(s.to_string());
+
+        Uhr::wire(&pairs(s).map_err(|_| invalid())?).ok_or_else(invalid)
+    }
+}
+
+#[cfg(test)]
+fn alpha(wiring: [Option<u8>; 26]) -> String {
+    wiring
+        .iter()
+        .enumerate()
+        .map(|(key, out)| (out.unwrap_or(key as u8) + b'A') as char)
+        .collect()
+}
+
+#[test]
+pub fn wire_uhr() {
+    let pairs = [
+        ('A', 'W'),
+        ('B', 'X'),
+        ('C', 'Y'),
+        ('D', 'Z'),
+        ('E', 'V'),
+        ('F', 'U'),
+        ('G', 'T'),
+        ('H', 'S'),
+        ('I', 'R'),
+        ('J', 'Q'),
+    ];
+
+    let mut uhr = Uhr::new(&pairs, 0).unwrap();
+    assert_eq!(alpha(uhr.wiring()), "WXYZVUTSRQKLMNOPJIHGFEABCD");
+
+    uhr.set(27).unwrap();
+    assert_eq!(alpha(uhr.wiring()), "WXVRTZUSQYKLMNOPBJAHEGDICF");
+
+    for setting in 0..40 {
+        uhr.set(setting).unwrap();
+        let wiring = uhr.wiring();
+        let reciprocal = (0..26).all(|key| match wiring[key] {
+            Some(out) => wiring[out as usize] == Some(key as u8),
+            None => true,
+        });
+        assert_eq!(reciprocal, setting % 4 == 0, "{:02}", setting);
+    }
+
+    assert!(uhr.set(40).is_err());
+    assert!(Uhr::new(&pairs[..9], 0).is_err());
+    assert!(Uhr::new(&[('A', 'A'); 10], 0).is_err());
+    assert!(Uhr::new(&[&pairs[1..], &[('A', 'B')]].concat(), 0).is_err());
+
+    // written out, the pairs keep their plug order
+    let uhr = "JQ AW BX CY DZ EV FU GT HS IR".parse::<Uhr>().unwrap();
+    assert_eq!(uhr.pairs()[..2], [('J', 'Q'), ('A', 'W')]);
+    assert_eq!(uhr.setting(), 0);
+    assert_eq!(
+        "AW BX CY".parse::<Uhr>(),
+        Err(EnigmaError::InvalidUhr("AW BX CY".to_string()))
+    );
+}
@@ -1,25 +1,121 @@
-macro_rules! abc {
-    () => {
-        ('A'..='Z')
-            .into_iter()
-            .collect::<Vec<_>>()
-            .into_iter()
-            .map(|x| x)
-            .map(|x| x as u32)
-            .collect::<Vec<u32>>()
-    };
-}
+use crate::error::EnigmaError;
 
 macro_rules! abc_index {
     ($s:expr) => {
-        ('A'..='Z')
-            .into_iter()
-            .collect::<Vec<_>>()
-            .into_iter()
-            .map(|x| x as u32)
-            .position(|x| x == ($s as u32))
+        ('A'..='Z').map(|x| x as u32).position(|x| x == ($s as u32))
     };
 }
 
-pub(crate) use abc;
+/// Parses a run of wheel settings written either as letters ("BUL", "B-U-L")
+/// or as two digit numbers ("02 21 12"), returning zero based offsets. The
+/// two don't mix: a number with a letter in it is no setting at all.
+pub(crate) fn settings(s: &str) -> Option<Vec<usize>> {
+    if s.chars().any(|c| c.is_ascii_digit()) {
+        return s
+            .split(|c: char| c.is_whitespace() || c == '-' || c == ',')
+            .filter(|n| !n.is_empty())
+            .map(|n| match n.parse::<usize>() {
+                Ok(n) if (1..=26).contains(&n) => Some(n - 1),
+                _ => None,
+            })
+            .collect();
+    }
+
+    s.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-' && *c != ',')
+        .map(|c| abc_index!(c.to_ascii_uppercase()))
+        .collect()
+}
+
+/// Reads cable pairs as written on a key sheet, "AV BS CG" or "AV-BS-CG",
+/// into letter offsets, keeping them in the order given.
+pub(crate) fn pairs(s: &str) -> Result<Vec<(u8, u8)>, EnigmaError> {
+    s.split([' ', '-', ','])
+        .filter(|p| !p.is_empty())
+        .map(|p| match p.chars().collect::<Vec<_>>()[..] {
+            [a, b] => match (
+                abc_index!(a.to_ascii_uppercase()),
+                abc_index!(b.to_ascii_uppercase()),
+            ) {
+                (Some(x), Some(y)) => Ok((x as u8, y as u8)),
+                _ => Err(EnigmaError::InvalidPlugPair(a, b)),
+            },
+            _ => Err(EnigmaError::InvalidPlugboard(p.to_string())),
+        })
+        .collect()
+}
+
+/// A small seeded generator (SplitMix64), so that key material drawn from
+/// the same seed comes out the same every time.
+pub(crate) struct Random(u64);
+
+impl Random {
+    pub(crate) fn new(seed: u64) -> Self {
+        Random(seed)
+    }
+
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number from 0 up to but not including `n`.
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        ((self.next() as u128 * n as u128) >> 64) as usize
+    }
+
+    pub(crate) fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+#[test]
+pub fn parse_settings() {
+    assert_eq!(settings("BUL"), Some(vec![1, 20, 11]));
+    assert_eq!(settings("b-u-l"), Some(vec![1, 20, 11]));
+    assert_eq!(settings("02 21 12"), Some(vec![1, 20, 11]));
+    assert_eq!(settings("2,21,12"), Some(vec![1, 20, 11]));
+    assert_eq!(settings("00 21 12"), None);
+    assert_eq!(settings("27"), None);
+    assert_eq!(settings("B?L"), None);
+    // letters aren't separators between numbers
+    assert_eq!(settings("1A2B3C"), None);
+    assert_eq!(settings("X1 Y2 Z3"), None);
+}
+
+#[test]
+pub fn parse_pairs() {
+    assert_eq!(
+        pairs("rx AV-BS,cg"),
+        Ok(vec![(17, 23), (0, 21), (1, 18), (2, 6)])
+    );
+    assert_eq!(pairs(" "), Ok(vec![]));
+    assert_eq!(pairs("AB A1"), Err(EnigmaError::InvalidPlugPair('A', '1')));
+    assert_eq!(
+        pairs("AB CDE"),
+        Err(EnigmaError::InvalidPlugboard("CDE".to_string()))
+    );
+}
+
+#[test]
+pub fn draw_random() {
+    let draw = |seed| {
+        let mut random = Random::new(seed);
+        (0..100).map(|_| random.below(26)).collect::<Vec<_>>()
+    };
 
+    assert_eq!(draw(1941), draw(1941));
+    assert_ne!(draw(1941), draw(1942));
+    assert!(draw(7).iter().all(|x| *x < 26));
+
+    let mut letters = (0..26).collect::<Vec<_>>();
+    Random::new(7).shuffle(&mut letters);
+    assert_ne!(letters, (0..26).collect::<Vec<_>>());
+    letters.sort();
+    assert_eq!(letters, (0..26).collect::<Vec<_>>());
+}
@@ -0,0 +1,126 @@
+//! Known-answer tests against authenticated wartime traffic. Each message is
+//! deciphered from its recorded key and the plaintext re-enciphered to get the
+//! original ciphertext back.
+
+use enigma::{Enigma, Plugboard};
+
+struct Message {
+    name: &'static str,
+    rotors: &'static [&'static str],
+    reflector: &'static str,
+    rings: &'static str,
+    plugs: &'static str,
+    start: &'static str,
+    ciphertext: &'static str,
+    plaintext: &'static str,
+}
+
+const BARBAROSSA_PLUGS: &str = "AV BS CG DL FU HZ IN KM OW RX";
+
+const CORPUS: &[Message] = &[
+    // Operation Barbarossa, 7 July 1941, part 1 of 2. Heeres M3.
+    Message {
+        name: "barbarossa part 1",
+        rotors: &["ii", "iv", "v"],
+        reflector: "ukwb",
+        rings: "02 21 12",
+        plugs: BARBAROSSA_PLUGS,
+        start: "BLA",
+        ciphertext: "EDPUD NRGYS ZRCXN UYTPO MRMBO FKTBZ REZKM LXLVE FGUEY SIOZV EQMIK UBPMM YLKLT TDEIS MDICA GYKUA CTCDO MOHWX MUUIA UBSTS LRNBZ SZWNR FXWFY SSXJZ VIJHI DISHP RKLKA YUPAD TXQSP INQMA TLPIF SVKDA SCTAC DPBOP VHJK",
+        plaintext: "AUFKL XABTE ILUNG XVONX KURTI NOWAX KURTI NOWAX NORDW ESTLX SEBEZ XSEBE ZXUAF FLIEG ERSTR ASZER IQTUN GXDUB ROWKI XDUBR OWKIX OPOTS CHKAX OPOTS CHKAX UMXEI NSAQT DREIN ULLXU HRANG ETRET ENXAN GRIFF XINFX RGTX",
+    },
+    // Operation Barbarossa, part 2 of 2.
+    Message {
+        name: "barbarossa part 2",
+        rotors: &["ii", "iv", "v"],
+        reflector: "ukwb",
+        rings: "02 21 12",
+        plugs: BARBAROSSA_PLUGS,
+        start: "LSD",
+        ciphertext: "SFBWD NJUSE GQOBH KRTAR EEZMW KPPRB XOHDR OEQGB BGTQV PGVKB VVGBI MHUSZ YDAJQ IROAX SSSNR EHYGG RPISE ZBOVM QIEMM ZCYSG QDGRE RVBIL EKXYQ IRGIR QNRDN VRXCY YTNJR",
+        plaintext: "DREIG EHTLA NGSAM ABERS IQERV ORWAE RTSXE INSSI EBENN ULLSE QSXUH RXROE MXEIN SXINF RGTXD REIXA UFFLI EGERS TRASZ EMITA NFANG XEINS SEQSX KMXKM XOSTW XKAME NECXK",
+    },
+    // Scharnhorst, sent 1943 on a Kriegsmarine M3.
+    Message {
+        name: "scharnhorst",
+        rotors: &["iii", "vi", "viii"],
+        reflector: "ukwb",
+        rings: "01 08 13",
+        plugs: "AN EZ HK IJ LR MQ OT PV SW UX",
+        start: "UZV",
+        ciphertext: "YKAE NZAP MSCH ZBFO CUVM RMDP YCOF HADZ IZME FXTH FLOL PZLF GGBO TGOX GRET DWTJ IQHL MXVJ WKZU ASTR",
+        plaintext: "STEU EREJ TANA FJOR DJAN STAN DORT QUAA ACCC VIER NEUN NEUN ZWOF AHRT ZWON ULSM XXSC HARN HORS THCO",
+    },
+    // U-264's "Looks" signal of November 1942. Four rotor naval M4 with the
+    // thin B reflector.
+    Message {
+        name: "u-264",
+        rotors: &["beta", "ii", "iv", "i"],
+        reflector: "bthin",
+        rings: "A A A V",
+        plugs: "AT BL DF GJ HM NW OP QY RZ VX",
+        start: "VJNA",
+        ciphertext: "NCZW VUSX PNYM INHZ XMQX SFWX WLKJ AHSH NMCO CCAK UQPM KCSM HKSE INJU SBLK IOSX CKUB HMLL XCSJ USRR DVKO HULX WCCB GVLI YXEO AHXR HKKF VDRE WEZL XOBA FGYU JQUK GRTV UKAM EURB VEKS UHHV OYHA BCJW MAKL FKLM YFVN RIZR VVRT KOFD ANJM OLBG FFLE OPRG TFLV RHOW OPBE KVWM UQFM PWPA RMFH AGKX IIBG",
+        plaintext: "VONV ONJL OOKS JHFF TTTE INSE INSD REIZ WOYY QNNS NEUN INHA LTXX BEIA NGRI FFUN TERW ASSE RGED RUEC KTYW ABOS XLET ZTER GEGN ERST ANDN ULAC HTDR EINU LUHR MARQ UANT ONJO TANE UNAC HTSE YHSD REIY ZWOZ WONU LGRA DYAC HTSM YSTO SSEN ACHX EKNS VIER MBFA ELLT YNNN NNNO OOVI ERYS ICHT EINS NULL",
+    },
+    // Dönitz announcing Hitler's succession, 1 May 1945, as received by
+    // U-534, sunk four days later. M4 with the thin C reflector. Only the
+    // opening 28 groups of the signal.
+    Message {
+        name: "u-534",
+        rotors: &["beta", "v", "vi", "viii"],
+        reflector: "cthin",
+        rings: "E P E L",
+        plugs: "AE BF CM DQ HU JN LX PR SZ VW",
+        start: "CDSZ",
+        ciphertext: "LANO TCTO UARB BFPM HPHG CZXT DYGA HGUF XGEW KBLK GJWL QXXT GPJJ AVTO CKZF SLPP QIHZ FXOE BWII EKFZ LCLO AQJU LJOY HSSM BBGW HZAN VOII PYRB",
+        plaintext: "KRKR ALLE XXFO LGEN DESI STSO FORT BEKA NNTZ UGEB ENXX ICHH ABEF OLGE LNBE BEFE HLER HALT ENXX JANS TERL EDES BISH ERIG XNRE ICHS MARS CHAL",
+    },
+];
+
+fn machine(message: &Message, start: &str) -> Enigma {
+    Enigma::from_config(
+        message.rotors,
+        message.reflector,
+        Some(start),
+        Some(message.rings),
+        Some(&message.plugs.parse::<Plugboard>().unwrap().pairs()),
+    )
+    .unwrap_or_else(|e| panic!("{}: {}", message.name, e))
+}
+
+#[test]
+pub fn decode_historical_messages() {
+    for message in CORPUS {
+        let mut enigma = machine(message, message.start);
+        assert_eq!(
+            enigma.try_decode(message.ciphertext).unwrap(),
+            message.plaintext,
+            "{}",
+            message.name
+        );
+    }
+}
+
+#[test]
+pub fn encode_historical_messages() {
+    for message in CORPUS {
+        let mut enigma = machine(message, message.start);
+        assert_eq!(
+            enigma.try_encode(message.plaintext).unwrap(),
+            message.ciphertext,
+            "{}",
+            message.name
+        );
+    }
+}
+
+#[test]
+pub fn decode_historical_indicators() {
+    // Barbarossa: the message keys went out enciphered at the Grundstellung
+    // given in clear in the preamble.
+    for (grundstellung, indicator, key) in [("WXC", "KCH", "BLA"), ("CRS", "YPJ", "LSD")] {
+        let mut enigma = machine(&CORPUS[0], grundstellung);
+        assert_eq!(enigma.decode(indicator), key);
+    }
+}
@@ -0,0 +1,157 @@
+//! Generative tests of the properties every correctly built Enigma has,
+//! whatever its rotor order, reflector, positions, rings and plugboard.
+
+use enigma::{Enigma, Rotor};
+use proptest::{prelude::*, sample::subsequence};
+use std::str::FromStr;
+
+const ROTORS: &[&str] = &["i", "ii", "iii", "iv", "v", "vi", "vii", "viii"];
+
+#[derive(Debug, Clone)]
+struct Config {
+    rotors: Vec<&'static str>,
+    reflector: &'static str,
+    positions: String,
+    rings: String,
+    plugs: Vec<(char, char)>,
+}
+
+impl Config {
+    fn machine(&self) -> Enigma {
+        Enigma::from_config(
+            &self.rotors,
+            self.reflector,
+            Some(&self.positions),
+            Some(&self.rings),
+            Some(&self.plugs),
+        )
+        .unwrap()
+    }
+}
+
+fn letters(len: usize) -> impl Strategy<Value = String> {
+    proptest::collection::vec(b'A'..=b'Z', len)
+        .prop_map(|l| l.into_iter().map(char::from).collect())
+}
+
+/// Up to thirteen cables, no letter plugged twice.
+fn plugs() -> impl Strategy<Value = Vec<(char, char)>> {
+    (
+        Just((b'A'..=b'Z').collect::<Vec<_>>()).prop_shuffle(),
+        0..=13usize,
+    )
+        .prop_map(|(letters, cables)| {
+            letters[..cables * 2]
+                .chunks(2)
+                .map(|p| (p[0] as char, p[1] as char))
+                .collect()
+        })
+}
+
+fn config() -> impl Strategy<Value = Config> {
+    let m3 = (
+        subsequence(ROTORS.to_vec(), 3).prop_shuffle(),
+        prop::sample::select(vec!["ukwb", "ukwc"]),
+    );
+    let m4 = (
+        prop::sample::select(vec!["beta", "gamma"]),
+        subsequence(ROTORS.to_vec(), 3).prop_shuffle(),
+        prop::sample::select(vec!["bthin", "cthin"]),
+    )
+        .prop_map(|(greek, rotors, reflector)| ([vec![greek], rotors].concat(), reflector));
+
+    prop_oneof![m3, m4].prop_flat_map(|(rotors, reflector)| {
+        let len = rotors.len();
+        (letters(len), letters(len), plugs()).prop_map(move |(positions, rings, plugs)| Config {
+            rotors: rotors.clone(),
+            reflector,
+            positions,
+            rings,
+            plugs,
+        })
+    })
+}
+
+proptest! {
+    #[test]
+    fn decode_inverts_encode(config in config(), plaintext in "[A-Z]{1,200}") {
+        let ciphertext = config.machine().encode(&plaintext);
+        prop_assert_eq!(config.machine().decode(&ciphertext), plaintext);
+    }
+
+    #[test]
+    fn no_letter_enciphers_to_itself(config in config(), plaintext in "[A-Z]{1,200}") {
+        let ciphertext = config.machine().encode(&plaintext);
+        for (p, c) in plaintext.chars().zip(ciphertext.chars()) {
+            prop_assert_ne!(p, c);
+        }
+    }
+
+    #[test]
+    fn every_position_is_an_involution(config in config(), prefix in "[A-Z]{0,60}") {
+        let mut enigma = config.machine();
+        enigma.encode(&prefix);
+
+        for letter in 'A'..='Z' {
+            let out = enigma.clone().encode(&letter.to_string());
+            let back = enigma.clone().encode(&out);
+            prop_assert_eq!(back, letter.to_string());
+        }
+    }
+
+    #[test]
+    fn seek_matches_stepping(config in config(), presses in 0..20_000usize) {
+        let mut enigma = config.machine();
+        let position = enigma.position_after(presses);
+        enigma.encode(&"A".repeat(presses));
+        prop_assert_eq!(enigma.positions(), position);
+
+        let state = enigma.snapshot();
+        enigma.seek(presses);
+        prop_assert_eq!(enigma.snapshot(), state);
+    }
+
+    #[test]
+    fn middle_rotor_double_steps(config in config(), presses in 1..700usize) {
+        let mut enigma = config.machine();
+        let len = config.rotors.len();
+
+        // notches and window letters of the three stepping rotors, fast rotor first
+        let notches = config.rotors[len - 3..]
+            .iter()
+            .rev()
+            .map(|r| Rotor::from_str(r).unwrap().turnovers().as_bytes().to_vec())
+            .collect::<Vec<_>>();
+        let mut windows = config.positions.as_bytes()[len - 3..]
+            .iter()
+            .rev()
+            .copied()
+            .collect::<Vec<_>>();
+        let advance = |w: &mut u8| *w = (*w - b'A' + 1) % 26 + b'A';
+
+        for _ in 0..presses {
+            if notches[1].contains(&windows[1]) {
+                advance(&mut windows[1]);
+                advance(&mut windows[2]);
+            } else if notches[0].contains(&windows[0]) {
+                advance(&mut windows[1]);
+            }
+            advance(&mut windows[0]);
+
+            enigma.encode("A");
+            let expected = config.positions[..len - 3].to_string()
+                + &windows.iter().rev().map(|w| *w as char).collect::<String>();
+            prop_assert_eq!(enigma.positions(), expected);
+        }
+    }
+}
+
+#[test]
+pub fn from_wheels_matches_from_config() {
+    let mut wheels = Enigma::from_wheels(&["beta", "ii", "iv", "i"], "bthin").unwrap();
+    let mut config =
+        Enigma::from_config(&["beta", "ii", "iv", "i"], "bthin", None, None, None).unwrap();
+
+    assert_eq!(wheels, config);
+    assert_eq!(wheels.encode("ENIGMA"), config.encode("ENIGMA"));
+}